@@ -0,0 +1,545 @@
+mod varint;
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Display, Formatter};
+use std::mem;
+
+use hashbrown::HashMap;
+
+use crate::models::{DamageStats, Encounter, EncounterEntity, SkillCast, SkillHit};
+use crate::schema::{SchemaError, encounter_from_json, encounter_to_json};
+
+use varint::{Reader, Writer};
+
+pub const ARCHIVE_MAGIC: &[u8; 4] = b"LMEA";
+
+/// Version written to the archive header.
+///
+/// Layout after the 6-byte header (`magic`, `version`, `codec`), possibly compressed by the codec:
+///
+/// 1. The encounter as JSON (see [`crate::schema`]) with the columns below emptied.
+/// 2. Buff table: every buff id referenced by a hit, sorted and delta encoded.
+/// 3. One record per entity, ordered by name, followed by a flagged record for `current_boss`:
+///    name, `dps_average` and `dps_rolling_10s_avg` (delta encoded), then per skill ordered by id:
+///    `cast_log` (delta encoded) and `skill_cast_log`, where casts and hits store timestamps as deltas
+///    and hits reference buffs by their index in the buff table.
+///
+/// Integers are LEB128 varints, signed values are zigzag encoded.
+pub const ARCHIVE_VERSION: u8 = 1;
+
+const HIT_CRIT: u8 = 1;
+const HIT_BACK_ATTACK: u8 = 1 << 1;
+const HIT_FRONT_ATTACK: u8 = 1 << 2;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Codec {
+    #[default]
+    None = 0,
+    #[cfg(feature = "archive-compression")]
+    Deflate = 1,
+}
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    InvalidMagic,
+    UnsupportedVersion(u8),
+    /// Unknown codec, or a compressed archive read without the `archive-compression` feature.
+    UnsupportedCodec(u8),
+    Truncated,
+    Corrupt(String),
+    Schema(SchemaError),
+    Io(std::io::Error),
+}
+
+impl Display for ArchiveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveError::InvalidMagic => write!(f, "not an encounter archive"),
+            ArchiveError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported archive version {}, expected 1 to {}",
+                version, ARCHIVE_VERSION
+            ),
+            ArchiveError::UnsupportedCodec(codec) => write!(f, "unsupported archive codec {}", codec),
+            ArchiveError::Truncated => write!(f, "archive is truncated"),
+            ArchiveError::Corrupt(reason) => write!(f, "corrupt archive: {}", reason),
+            ArchiveError::Schema(err) => write!(f, "{}", err),
+            ArchiveError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ArchiveError {}
+
+impl From<SchemaError> for ArchiveError {
+    fn from(err: SchemaError) -> Self {
+        ArchiveError::Schema(err)
+    }
+}
+
+impl From<serde_json::Error> for ArchiveError {
+    fn from(err: serde_json::Error) -> Self {
+        ArchiveError::Schema(SchemaError::Json(err))
+    }
+}
+
+impl From<std::io::Error> for ArchiveError {
+    fn from(err: std::io::Error) -> Self {
+        ArchiveError::Io(err)
+    }
+}
+
+pub fn encode_encounter(encounter: &Encounter, codec: Codec) -> Result<Vec<u8>, ArchiveError> {
+    let mut skeleton = encounter.clone();
+    let mut records: Vec<(String, EntityColumns)> = skeleton
+        .entities
+        .iter_mut()
+        .map(|(name, entity)| (name.clone(), EntityColumns::take(entity)))
+        .collect();
+    records.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let current_boss = skeleton.current_boss.as_mut().map(EntityColumns::take);
+
+    let buff_ids: BTreeSet<u32> = records
+        .iter()
+        .map(|(_, columns)| columns)
+        .chain(&current_boss)
+        .flat_map(EntityColumns::buff_ids)
+        .collect();
+    let buff_index: HashMap<u32, u64> = buff_ids.iter().enumerate().map(|(index, &id)| (id, index as u64)).collect();
+
+    let mut writer = Writer::default();
+    writer.bytes(&serde_json::to_vec(&encounter_to_json(&skeleton)?)?);
+
+    writer.unsigned(buff_ids.len() as u64);
+    let mut previous = 0;
+    for &id in &buff_ids {
+        writer.unsigned((id - previous) as u64);
+        previous = id;
+    }
+
+    writer.unsigned(records.len() as u64);
+    for (name, columns) in &records {
+        writer.string(name);
+        columns.write(&mut writer, &buff_index);
+    }
+
+    match &current_boss {
+        Some(columns) => {
+            writer.unsigned(1);
+            columns.write(&mut writer, &buff_index);
+        }
+        None => writer.unsigned(0),
+    }
+
+    let mut archive = Vec::with_capacity(writer.len() + 6);
+    archive.extend_from_slice(ARCHIVE_MAGIC);
+    archive.push(ARCHIVE_VERSION);
+    archive.push(codec as u8);
+    archive.extend(compress(codec, writer.into_inner())?);
+
+    Ok(archive)
+}
+
+pub fn decode_encounter(archive: &[u8]) -> Result<Encounter, ArchiveError> {
+    if archive.len() < 6 {
+        return Err(ArchiveError::Truncated);
+    }
+
+    if &archive[..4] != ARCHIVE_MAGIC {
+        return Err(ArchiveError::InvalidMagic);
+    }
+
+    let version = archive[4];
+    if version == 0 || version > ARCHIVE_VERSION {
+        return Err(ArchiveError::UnsupportedVersion(version));
+    }
+
+    let body = decompress(archive[5], &archive[6..])?;
+    let mut reader = Reader::new(&body);
+
+    let mut encounter = encounter_from_json(serde_json::from_slice(reader.bytes()?)?)?;
+
+    let buff_count = reader.len()?;
+    let mut buff_ids = Vec::with_capacity(buff_count);
+    let mut previous = 0u32;
+    for _ in 0..buff_count {
+        previous = previous
+            .checked_add(reader.u32()?)
+            .ok_or_else(|| ArchiveError::Corrupt("buff id overflow".to_string()))?;
+        buff_ids.push(previous);
+    }
+
+    for _ in 0..reader.len()? {
+        let name = reader.string()?;
+        let entity = encounter
+            .entities
+            .get_mut(&name)
+            .ok_or_else(|| ArchiveError::Corrupt(format!("unknown entity {}", name)))?;
+        EntityColumns::read(&mut reader, &buff_ids)?.restore(entity)?;
+    }
+
+    if reader.unsigned()? == 1 {
+        let columns = EntityColumns::read(&mut reader, &buff_ids)?;
+        let boss = encounter
+            .current_boss
+            .as_mut()
+            .ok_or_else(|| ArchiveError::Corrupt("missing current boss".to_string()))?;
+        columns.restore(boss)?;
+    }
+
+    if !reader.is_empty() {
+        return Err(ArchiveError::Corrupt("trailing bytes".to_string()));
+    }
+
+    Ok(encounter)
+}
+
+/// Bulky vectors of an entity which are stored column-wise instead of as JSON.
+struct EntityColumns {
+    dps_average: Vec<i64>,
+    dps_rolling_10s_avg: Vec<i64>,
+    skills: Vec<(u32, Vec<i32>, Vec<SkillCast>)>,
+}
+
+impl EntityColumns {
+    fn take(entity: &mut EncounterEntity) -> Self {
+        let DamageStats {
+            dps_average,
+            dps_rolling_10s_avg,
+            ..
+        } = &mut entity.damage_stats;
+
+        let mut skills: Vec<_> = entity
+            .skills
+            .iter_mut()
+            .map(|(&id, skill)| (id, mem::take(&mut skill.cast_log), mem::take(&mut skill.skill_cast_log)))
+            .collect();
+        skills.sort_by_key(|(id, _, _)| *id);
+
+        Self {
+            dps_average: mem::take(dps_average),
+            dps_rolling_10s_avg: mem::take(dps_rolling_10s_avg),
+            skills,
+        }
+    }
+
+    fn restore(self, entity: &mut EncounterEntity) -> Result<(), ArchiveError> {
+        entity.damage_stats.dps_average = self.dps_average;
+        entity.damage_stats.dps_rolling_10s_avg = self.dps_rolling_10s_avg;
+
+        for (id, cast_log, skill_cast_log) in self.skills {
+            let skill = entity
+                .skills
+                .get_mut(&id)
+                .ok_or_else(|| ArchiveError::Corrupt(format!("unknown skill {} of {}", id, entity.name)))?;
+            skill.cast_log = cast_log;
+            skill.skill_cast_log = skill_cast_log;
+        }
+
+        Ok(())
+    }
+
+    fn buff_ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.skills
+            .iter()
+            .flat_map(|(_, _, casts)| casts)
+            .flat_map(|cast| &cast.hits)
+            .flat_map(|hit| hit.buffed_by.iter().chain(&hit.debuffed_by))
+            .copied()
+    }
+
+    fn write(&self, writer: &mut Writer, buff_index: &HashMap<u32, u64>) {
+        writer.deltas(&self.dps_average);
+        writer.deltas(&self.dps_rolling_10s_avg);
+
+        writer.unsigned(self.skills.len() as u64);
+        for (id, cast_log, skill_cast_log) in &self.skills {
+            writer.unsigned(*id as u64);
+            writer.deltas(&cast_log.iter().map(|&timestamp| timestamp as i64).collect::<Vec<_>>());
+
+            writer.unsigned(skill_cast_log.len() as u64);
+            let mut previous_cast = 0;
+            for cast in skill_cast_log {
+                writer.signed(cast.timestamp - previous_cast);
+                writer.signed(cast.last - cast.timestamp);
+                previous_cast = cast.timestamp;
+
+                writer.unsigned(cast.hits.len() as u64);
+                let mut previous_hit = cast.timestamp;
+                for hit in &cast.hits {
+                    writer.signed(hit.timestamp - previous_hit);
+                    previous_hit = hit.timestamp;
+                    writer.signed(hit.damage);
+                    writer.u8(
+                        (hit.crit as u8 * HIT_CRIT)
+                            | (hit.back_attack as u8 * HIT_BACK_ATTACK)
+                            | (hit.front_attack as u8 * HIT_FRONT_ATTACK),
+                    );
+                    for ids in [&hit.buffed_by, &hit.debuffed_by] {
+                        writer.unsigned(ids.len() as u64);
+                        for id in ids {
+                            writer.unsigned(buff_index[id]);
+                        }
+                    }
+                    writer.signed(hit.rdps_damage_received);
+                    writer.signed(hit.rdps_damage_received_support);
+                }
+            }
+        }
+    }
+
+    fn read(reader: &mut Reader, buff_ids: &[u32]) -> Result<Self, ArchiveError> {
+        let dps_average = reader.deltas()?;
+        let dps_rolling_10s_avg = reader.deltas()?;
+
+        let skill_count = reader.len()?;
+        let mut skills = Vec::with_capacity(skill_count);
+        for _ in 0..skill_count {
+            let id = reader.u32()?;
+            let cast_log = reader
+                .deltas()?
+                .into_iter()
+                .map(|timestamp| {
+                    i32::try_from(timestamp).map_err(|_| ArchiveError::Corrupt("cast log overflow".to_string()))
+                })
+                .collect::<Result<_, _>>()?;
+
+            let cast_count = reader.len()?;
+            let mut skill_cast_log = Vec::with_capacity(cast_count);
+            let mut previous_cast = 0;
+            for _ in 0..cast_count {
+                let timestamp = add_delta(previous_cast, reader.signed()?)?;
+                let last = add_delta(timestamp, reader.signed()?)?;
+                previous_cast = timestamp;
+
+                let hit_count = reader.len()?;
+                let mut hits = Vec::with_capacity(hit_count);
+                let mut previous_hit = timestamp;
+                for _ in 0..hit_count {
+                    previous_hit = add_delta(previous_hit, reader.signed()?)?;
+                    let damage = reader.signed()?;
+                    let flags = reader.u8()?;
+                    let buffed_by = read_buff_ids(reader, buff_ids)?;
+                    let debuffed_by = read_buff_ids(reader, buff_ids)?;
+
+                    hits.push(SkillHit {
+                        timestamp: previous_hit,
+                        damage,
+                        crit: flags & HIT_CRIT != 0,
+                        back_attack: flags & HIT_BACK_ATTACK != 0,
+                        front_attack: flags & HIT_FRONT_ATTACK != 0,
+                        buffed_by,
+                        debuffed_by,
+                        rdps_damage_received: reader.signed()?,
+                        rdps_damage_received_support: reader.signed()?,
+                    });
+                }
+
+                skill_cast_log.push(SkillCast { timestamp, last, hits });
+            }
+
+            skills.push((id, cast_log, skill_cast_log));
+        }
+
+        Ok(Self {
+            dps_average,
+            dps_rolling_10s_avg,
+            skills,
+        })
+    }
+}
+
+/// Applies a delta read from the archive, which may be corrupt and overflow.
+fn add_delta(base: i64, delta: i64) -> Result<i64, ArchiveError> {
+    base.checked_add(delta)
+        .ok_or_else(|| ArchiveError::Corrupt("timestamp delta overflow".to_string()))
+}
+
+fn read_buff_ids(reader: &mut Reader, buff_ids: &[u32]) -> Result<Vec<u32>, ArchiveError> {
+    (0..reader.len()?)
+        .map(|_| {
+            let index = reader.unsigned()? as usize;
+            buff_ids
+                .get(index)
+                .copied()
+                .ok_or_else(|| ArchiveError::Corrupt(format!("buff index {} out of range", index)))
+        })
+        .collect()
+}
+
+fn compress(codec: Codec, body: Vec<u8>) -> Result<Vec<u8>, ArchiveError> {
+    match codec {
+        Codec::None => Ok(body),
+        #[cfg(feature = "archive-compression")]
+        Codec::Deflate => {
+            use std::io::Write;
+
+            let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::best());
+            encoder.write_all(&body)?;
+            Ok(encoder.finish()?)
+        }
+    }
+}
+
+fn decompress(codec: u8, body: &[u8]) -> Result<Vec<u8>, ArchiveError> {
+    match codec {
+        0 => Ok(body.to_vec()),
+        #[cfg(feature = "archive-compression")]
+        1 => {
+            use std::io::Read;
+
+            let mut decoded = Vec::new();
+            flate2::read::DeflateDecoder::new(body).read_to_end(&mut decoded)?;
+            Ok(decoded)
+        }
+        codec => Err(ArchiveError::UnsupportedCodec(codec)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::models::*;
+
+    use super::*;
+
+    fn json(encounter: &Encounter) -> Value {
+        serde_json::to_value(encounter).unwrap()
+    }
+
+    fn large_encounter() -> Encounter {
+        let mut encounter = Encounter::default();
+        for player in 0..8 {
+            let name = format!("Player{}", player);
+            let mut entity = EncounterEntity {
+                name: name.clone(),
+                entity_type: EntityType::Player,
+                damage_stats: DamageStats {
+                    dps_average: (0..600).map(|second| 100_000 + second * 7).collect(),
+                    dps_rolling_10s_avg: (0..600).map(|second| 90_000 + (second % 13) * 1000).collect(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            for skill_id in 0..10u32 {
+                let casts: Vec<SkillCast> = (0..60i64)
+                    .map(|cast| {
+                        let timestamp = cast * 10_000 + skill_id as i64 * 100;
+                        SkillCast {
+                            timestamp,
+                            last: timestamp + 900,
+                            hits: (0..5i64)
+                                .map(|hit| SkillHit {
+                                    timestamp: timestamp + hit * 150,
+                                    damage: 1_000_000 + hit * 12_345,
+                                    crit: hit % 2 == 0,
+                                    back_attack: hit % 3 == 0,
+                                    buffed_by: vec![362600, 211400 + hit as u32],
+                                    debuffed_by: vec![210230],
+                                    ..Default::default()
+                                })
+                                .collect(),
+                        }
+                    })
+                    .collect();
+                entity.skills.insert(
+                    skill_id,
+                    Skill {
+                        id: skill_id,
+                        cast_log: casts.iter().map(|cast| cast.timestamp as i32).collect(),
+                        skill_cast_log: casts,
+                        ..Default::default()
+                    },
+                );
+            }
+            encounter.entities.insert(name, entity);
+        }
+        encounter
+    }
+
+    #[test]
+    fn should_round_trip_fixture_losslessly() {
+        let encounter: Encounter = serde_json::from_str(include_str!("../../tests/fixtures/encounter.json")).unwrap();
+
+        let decoded = decode_encounter(&encode_encounter(&encounter, Codec::None).unwrap()).unwrap();
+
+        assert_eq!(json(&decoded), json(&encounter));
+    }
+
+    #[test]
+    fn should_round_trip_large_encounter_and_be_smaller_than_json() {
+        let mut encounter = large_encounter();
+        encounter.current_boss = Some(EncounterEntity {
+            name: "Thaemine".to_string(),
+            damage_stats: DamageStats {
+                dps_average: vec![5, 3, -2],
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        let archive = encode_encounter(&encounter, Codec::None).unwrap();
+        let decoded = decode_encounter(&archive).unwrap();
+
+        assert_eq!(json(&decoded), json(&encounter));
+        assert!(archive.len() * 4 < serde_json::to_vec(&encounter).unwrap().len());
+    }
+
+    #[cfg(feature = "archive-compression")]
+    #[test]
+    fn should_round_trip_compressed_archive() {
+        let encounter = large_encounter();
+
+        let plain = encode_encounter(&encounter, Codec::None).unwrap();
+        let compressed = encode_encounter(&encounter, Codec::Deflate).unwrap();
+
+        assert!(compressed.len() < plain.len());
+        assert_eq!(json(&decode_encounter(&compressed).unwrap()), json(&encounter));
+    }
+
+    #[test]
+    fn should_reject_invalid_archives() {
+        let mut archive = encode_encounter(&Encounter::default(), Codec::None).unwrap();
+
+        assert!(matches!(decode_encounter(b"LME"), Err(ArchiveError::Truncated)));
+        assert!(matches!(decode_encounter(b"JSON{}"), Err(ArchiveError::InvalidMagic)));
+        assert!(matches!(
+            decode_encounter(&archive[..archive.len() - 1]),
+            Err(ArchiveError::Truncated)
+        ));
+
+        archive[5] = 9;
+        assert!(matches!(decode_encounter(&archive), Err(ArchiveError::UnsupportedCodec(9))));
+
+        archive[4] = ARCHIVE_VERSION + 1;
+        assert!(matches!(
+            decode_encounter(&archive),
+            Err(ArchiveError::UnsupportedVersion(_))
+        ));
+
+        archive[4] = 0;
+        assert!(matches!(decode_encounter(&archive), Err(ArchiveError::UnsupportedVersion(0))));
+    }
+
+    #[test]
+    fn should_reject_overflowing_deltas() {
+        let mut writer = Writer::default();
+        writer.deltas(&[]);
+        writer.deltas(&[]);
+        writer.unsigned(1);
+        writer.unsigned(16140);
+        writer.deltas(&[]);
+        writer.unsigned(1);
+        writer.signed(i64::MAX);
+        writer.signed(1);
+        writer.unsigned(0);
+        let body = writer.into_inner();
+
+        assert!(matches!(
+            EntityColumns::read(&mut Reader::new(&body), &[]),
+            Err(ArchiveError::Corrupt(_))
+        ));
+    }
+}
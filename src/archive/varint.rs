@@ -0,0 +1,121 @@
+use super::ArchiveError;
+
+#[derive(Default)]
+pub(super) struct Writer(Vec<u8>);
+
+impl Writer {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.0
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    pub fn unsigned(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+
+    pub fn signed(&mut self, value: i64) {
+        self.unsigned(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.unsigned(bytes.len() as u64);
+        self.0.extend_from_slice(bytes);
+    }
+
+    pub fn string(&mut self, value: &str) {
+        self.bytes(value.as_bytes());
+    }
+
+    /// Writes the length followed by the differences between consecutive values.
+    pub fn deltas(&mut self, values: &[i64]) {
+        self.unsigned(values.len() as u64);
+        let mut previous = 0i64;
+        for &value in values {
+            self.signed(value.wrapping_sub(previous));
+            previous = value;
+        }
+    }
+}
+
+pub(super) struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn u8(&mut self) -> Result<u8, ArchiveError> {
+        let (&value, rest) = self.bytes.split_first().ok_or(ArchiveError::Truncated)?;
+        self.bytes = rest;
+        Ok(value)
+    }
+
+    pub fn unsigned(&mut self) -> Result<u64, ArchiveError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(ArchiveError::Corrupt("varint too long".to_string()))
+    }
+
+    pub fn signed(&mut self) -> Result<i64, ArchiveError> {
+        let value = self.unsigned()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, ArchiveError> {
+        u32::try_from(self.unsigned()?).map_err(|_| ArchiveError::Corrupt("value exceeds u32".to_string()))
+    }
+
+    /// Reads a length, rejecting values larger than the remaining input.
+    pub fn len(&mut self) -> Result<usize, ArchiveError> {
+        let len = self.unsigned()?;
+        if len > self.bytes.len() as u64 {
+            return Err(ArchiveError::Truncated);
+        }
+        Ok(len as usize)
+    }
+
+    pub fn bytes(&mut self) -> Result<&'a [u8], ArchiveError> {
+        let len = self.len()?;
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    pub fn string(&mut self) -> Result<String, ArchiveError> {
+        String::from_utf8(self.bytes()?.to_vec()).map_err(|_| ArchiveError::Corrupt("invalid utf-8".to_string()))
+    }
+
+    pub fn deltas(&mut self) -> Result<Vec<i64>, ArchiveError> {
+        let len = self.len()?;
+        let mut values = Vec::with_capacity(len);
+        let mut previous = 0i64;
+        for _ in 0..len {
+            previous = previous.wrapping_add(self.signed()?);
+            values.push(previous);
+        }
+        Ok(values)
+    }
+}
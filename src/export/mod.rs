@@ -0,0 +1,387 @@
+#[cfg(feature = "export-parquet")]
+mod parquet;
+
+use std::fmt::{self, Display, Formatter};
+use std::io::Write;
+
+use crate::models::{Encounter, EncounterEntity, Skill};
+
+#[cfg(feature = "export-parquet")]
+pub use parquet::*;
+
+/// Columns of the per-hit table, in order.
+///
+/// | Column | Type | Description |
+/// |--------|------|-------------|
+/// | `player` | string | Entity name |
+/// | `class` | string | Class name, empty for non-players |
+/// | `skill_id` | u32 | |
+/// | `skill_name` | string | |
+/// | `cast_timestamp` | i64 | Start of the cast, ms since fight start |
+/// | `timestamp` | i64 | ms since fight start |
+/// | `damage` | i64 | |
+/// | `crit` | bool | |
+/// | `back_attack` | bool | |
+/// | `front_attack` | bool | |
+/// | `buffed_by` | list of u32 | Status effect ids; `|`-separated in CSV |
+/// | `debuffed_by` | list of u32 | Status effect ids; `|`-separated in CSV |
+/// | `rdps_damage_received` | i64 | |
+/// | `rdps_damage_received_support` | i64 | |
+pub const HIT_COLUMNS: [&str; 14] = [
+    "player",
+    "class",
+    "skill_id",
+    "skill_name",
+    "cast_timestamp",
+    "timestamp",
+    "damage",
+    "crit",
+    "back_attack",
+    "front_attack",
+    "buffed_by",
+    "debuffed_by",
+    "rdps_damage_received",
+    "rdps_damage_received_support",
+];
+
+/// Columns of the per-skill table, in order. All columns besides `player`, `class` and
+/// `skill_name` (strings) and `skill_id` (u32) are i64 copies of the [`Skill`] fields.
+pub const SKILL_COLUMNS: [&str; 18] = [
+    "player",
+    "class",
+    "skill_id",
+    "skill_name",
+    "total_damage",
+    "max_damage",
+    "casts",
+    "hits",
+    "crits",
+    "crit_damage",
+    "back_attacks",
+    "front_attacks",
+    "back_attack_damage",
+    "front_attack_damage",
+    "buffed_by_support",
+    "buffed_by_identity",
+    "debuffed_by_support",
+    "dps",
+];
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct HitRow {
+    pub player: String,
+    pub class: String,
+    pub skill_id: u32,
+    pub skill_name: String,
+    pub cast_timestamp: i64,
+    pub timestamp: i64,
+    pub damage: i64,
+    pub crit: bool,
+    pub back_attack: bool,
+    pub front_attack: bool,
+    pub buffed_by: Vec<u32>,
+    pub debuffed_by: Vec<u32>,
+    pub rdps_damage_received: i64,
+    pub rdps_damage_received_support: i64,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SkillRow {
+    pub player: String,
+    pub class: String,
+    pub skill_id: u32,
+    pub skill_name: String,
+    pub total_damage: i64,
+    pub max_damage: i64,
+    pub casts: i64,
+    pub hits: i64,
+    pub crits: i64,
+    pub crit_damage: i64,
+    pub back_attacks: i64,
+    pub front_attacks: i64,
+    pub back_attack_damage: i64,
+    pub front_attack_damage: i64,
+    pub buffed_by_support: i64,
+    pub buffed_by_identity: i64,
+    pub debuffed_by_support: i64,
+    pub dps: i64,
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    Csv(csv::Error),
+    Io(std::io::Error),
+    #[cfg(feature = "export-parquet")]
+    Parquet(::parquet::errors::ParquetError),
+    #[cfg(feature = "export-parquet")]
+    Arrow(arrow_schema::ArrowError),
+}
+
+impl Display for ExportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Csv(err) => write!(f, "{}", err),
+            ExportError::Io(err) => write!(f, "{}", err),
+            #[cfg(feature = "export-parquet")]
+            ExportError::Parquet(err) => write!(f, "{}", err),
+            #[cfg(feature = "export-parquet")]
+            ExportError::Arrow(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+impl From<csv::Error> for ExportError {
+    fn from(err: csv::Error) -> Self {
+        ExportError::Csv(err)
+    }
+}
+
+impl From<std::io::Error> for ExportError {
+    fn from(err: std::io::Error) -> Self {
+        ExportError::Io(err)
+    }
+}
+
+/// Entities which used skills, ordered by name, with their skills ordered by id.
+fn skills_by_entity(encounter: &Encounter) -> impl Iterator<Item = (&EncounterEntity, &Skill)> {
+    let mut entities: Vec<&EncounterEntity> = encounter.entities.values().collect();
+    entities.sort_by(|a, b| a.name.cmp(&b.name));
+
+    entities.into_iter().flat_map(|entity| {
+        let mut skills: Vec<&Skill> = entity.skills.values().collect();
+        skills.sort_by_key(|skill| skill.id);
+        skills.into_iter().map(move |skill| (entity, skill))
+    })
+}
+
+/// One row per [`SkillHit`](crate::models::SkillHit), ordered by player, skill and time.
+pub fn hit_rows(encounter: &Encounter) -> Vec<HitRow> {
+    skills_by_entity(encounter)
+        .flat_map(|(entity, skill)| {
+            skill.skill_cast_log.iter().flat_map(move |cast| {
+                cast.hits.iter().map(move |hit| HitRow {
+                    player: entity.name.clone(),
+                    class: entity.class.clone(),
+                    skill_id: skill.id,
+                    skill_name: skill.name.clone(),
+                    cast_timestamp: cast.timestamp,
+                    timestamp: hit.timestamp,
+                    damage: hit.damage,
+                    crit: hit.crit,
+                    back_attack: hit.back_attack,
+                    front_attack: hit.front_attack,
+                    buffed_by: hit.buffed_by.clone(),
+                    debuffed_by: hit.debuffed_by.clone(),
+                    rdps_damage_received: hit.rdps_damage_received,
+                    rdps_damage_received_support: hit.rdps_damage_received_support,
+                })
+            })
+        })
+        .collect()
+}
+
+pub fn skill_rows(encounter: &Encounter) -> Vec<SkillRow> {
+    skills_by_entity(encounter)
+        .map(|(entity, skill)| SkillRow {
+            player: entity.name.clone(),
+            class: entity.class.clone(),
+            skill_id: skill.id,
+            skill_name: skill.name.clone(),
+            total_damage: skill.total_damage,
+            max_damage: skill.max_damage,
+            casts: skill.casts,
+            hits: skill.hits,
+            crits: skill.crits,
+            crit_damage: skill.crit_damage,
+            back_attacks: skill.back_attacks,
+            front_attacks: skill.front_attacks,
+            back_attack_damage: skill.back_attack_damage,
+            front_attack_damage: skill.front_attack_damage,
+            buffed_by_support: skill.buffed_by_support,
+            buffed_by_identity: skill.buffed_by_identity,
+            debuffed_by_support: skill.debuffed_by_support,
+            dps: skill.dps,
+        })
+        .collect()
+}
+
+fn join_ids(ids: &[u32]) -> String {
+    ids.iter().map(u32::to_string).collect::<Vec<_>>().join("|")
+}
+
+/// Writes [`hit_rows`] as CSV with a [`HIT_COLUMNS`] header.
+pub fn write_hits_csv<W: Write>(encounter: &Encounter, writer: W) -> Result<(), ExportError> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(HIT_COLUMNS)?;
+
+    for row in hit_rows(encounter) {
+        writer.write_record([
+            row.player,
+            row.class,
+            row.skill_id.to_string(),
+            row.skill_name,
+            row.cast_timestamp.to_string(),
+            row.timestamp.to_string(),
+            row.damage.to_string(),
+            row.crit.to_string(),
+            row.back_attack.to_string(),
+            row.front_attack.to_string(),
+            join_ids(&row.buffed_by),
+            join_ids(&row.debuffed_by),
+            row.rdps_damage_received.to_string(),
+            row.rdps_damage_received_support.to_string(),
+        ])?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes [`skill_rows`] as CSV with a [`SKILL_COLUMNS`] header.
+pub fn write_skills_csv<W: Write>(encounter: &Encounter, writer: W) -> Result<(), ExportError> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(SKILL_COLUMNS)?;
+
+    for row in skill_rows(encounter) {
+        writer.write_record([
+            row.player,
+            row.class,
+            row.skill_id.to_string(),
+            row.skill_name,
+            row.total_damage.to_string(),
+            row.max_damage.to_string(),
+            row.casts.to_string(),
+            row.hits.to_string(),
+            row.crits.to_string(),
+            row.crit_damage.to_string(),
+            row.back_attacks.to_string(),
+            row.front_attacks.to_string(),
+            row.back_attack_damage.to_string(),
+            row.front_attack_damage.to_string(),
+            row.buffed_by_support.to_string(),
+            row.buffed_by_identity.to_string(),
+            row.debuffed_by_support.to_string(),
+            row.dps.to_string(),
+        ])?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::*;
+
+    use super::*;
+
+    pub(super) fn encounter() -> Encounter {
+        let skill = |id: u32, name: &str, hits: Vec<SkillHit>| Skill {
+            id,
+            name: name.to_string(),
+            total_damage: hits.iter().map(|hit| hit.damage).sum(),
+            hits: hits.len() as i64,
+            casts: 1,
+            skill_cast_log: vec![SkillCast {
+                timestamp: hits[0].timestamp,
+                last: hits[hits.len() - 1].timestamp,
+                hits,
+            }],
+            ..Default::default()
+        };
+        let bard = EncounterEntity {
+            name: "Bob".to_string(),
+            class: "Bard".to_string(),
+            skills: [(
+                21070,
+                skill(21070, "Sound Shock", vec![SkillHit {
+                    timestamp: 500,
+                    damage: 100,
+                    ..Default::default()
+                }]),
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+        let berserker = EncounterEntity {
+            name: "Alice".to_string(),
+            class: "Berserker".to_string(),
+            skills: [(
+                16140,
+                skill(16140, "Bloody Rush, Finisher", vec![
+                    SkillHit {
+                        timestamp: 1000,
+                        damage: 5000,
+                        crit: true,
+                        back_attack: true,
+                        buffed_by: vec![211400, 362600],
+                        ..Default::default()
+                    },
+                    SkillHit {
+                        timestamp: 1200,
+                        damage: 3000,
+                        debuffed_by: vec![210230],
+                        ..Default::default()
+                    },
+                ]),
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+
+        Encounter {
+            entities: [(bard.name.clone(), bard), (berserker.name.clone(), berserker)]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn should_flatten_hits_in_stable_order() {
+        let rows = hit_rows(&encounter());
+
+        assert_eq!(
+            rows.iter().map(|row| (row.player.as_str(), row.timestamp)).collect::<Vec<_>>(),
+            [("Alice", 1000), ("Alice", 1200), ("Bob", 500)]
+        );
+        assert_eq!(rows[0].buffed_by, [211400, 362600]);
+        assert_eq!(rows[1].cast_timestamp, 1000);
+    }
+
+    #[test]
+    fn should_write_hits_csv() {
+        let mut csv = Vec::new();
+
+        write_hits_csv(&encounter(), &mut csv).unwrap();
+
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], HIT_COLUMNS.join(","));
+        assert_eq!(
+            lines[1],
+            "Alice,Berserker,16140,\"Bloody Rush, Finisher\",1000,1000,5000,true,true,false,211400|362600,,0,0"
+        );
+        assert_eq!(lines.len(), 4);
+    }
+
+    #[test]
+    fn should_write_skills_csv() {
+        let mut csv = Vec::new();
+
+        write_skills_csv(&encounter(), &mut csv).unwrap();
+
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], SKILL_COLUMNS.join(","));
+        assert_eq!(
+            lines[1],
+            "Alice,Berserker,16140,\"Bloody Rush, Finisher\",8000,0,1,2,0,0,0,0,0,0,0,0,0,0"
+        );
+        assert_eq!(lines[2], "Bob,Bard,21070,Sound Shock,100,0,1,1,0,0,0,0,0,0,0,0,0,0");
+    }
+}
@@ -0,0 +1,203 @@
+use std::io::Write;
+use std::sync::Arc;
+
+use arrow_array::builder::{ListBuilder, UInt32Builder};
+use arrow_array::{ArrayRef, BooleanArray, Int64Array, RecordBatch, StringArray, UInt32Array};
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+use parquet::errors::ParquetError;
+
+use crate::models::Encounter;
+
+use super::{ExportError, HIT_COLUMNS, HitRow, SKILL_COLUMNS, SkillRow, hit_rows, skill_rows};
+
+impl From<ParquetError> for ExportError {
+    fn from(err: ParquetError) -> Self {
+        ExportError::Parquet(err)
+    }
+}
+
+impl From<ArrowError> for ExportError {
+    fn from(err: ArrowError) -> Self {
+        ExportError::Arrow(err)
+    }
+}
+
+fn id_list_type() -> DataType {
+    DataType::List(Arc::new(Field::new("item", DataType::UInt32, true)))
+}
+
+/// Arrow schema of the per-hit table, see [`HIT_COLUMNS`].
+pub fn hit_schema() -> Schema {
+    let types = [
+        DataType::Utf8,
+        DataType::Utf8,
+        DataType::UInt32,
+        DataType::Utf8,
+        DataType::Int64,
+        DataType::Int64,
+        DataType::Int64,
+        DataType::Boolean,
+        DataType::Boolean,
+        DataType::Boolean,
+        id_list_type(),
+        id_list_type(),
+        DataType::Int64,
+        DataType::Int64,
+    ];
+
+    Schema::new(
+        HIT_COLUMNS
+            .iter()
+            .zip(types)
+            .map(|(name, data_type)| Field::new(*name, data_type, false))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Arrow schema of the per-skill table, see [`SKILL_COLUMNS`].
+pub fn skill_schema() -> Schema {
+    Schema::new(
+        SKILL_COLUMNS
+            .iter()
+            .map(|&name| {
+                let data_type = match name {
+                    "player" | "class" | "skill_name" => DataType::Utf8,
+                    "skill_id" => DataType::UInt32,
+                    _ => DataType::Int64,
+                };
+                Field::new(name, data_type, false)
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn strings<T>(rows: &[T], value: impl Fn(&T) -> &str) -> ArrayRef {
+    Arc::new(StringArray::from_iter_values(rows.iter().map(value)))
+}
+
+fn integers<T>(rows: &[T], value: impl Fn(&T) -> i64) -> ArrayRef {
+    Arc::new(Int64Array::from_iter_values(rows.iter().map(value)))
+}
+
+fn booleans<T>(rows: &[T], value: impl Fn(&T) -> bool) -> ArrayRef {
+    Arc::new(BooleanArray::from_iter(rows.iter().map(|row| Some(value(row)))))
+}
+
+fn id_lists<T>(rows: &[T], value: impl Fn(&T) -> &[u32]) -> ArrayRef {
+    let mut builder = ListBuilder::new(UInt32Builder::new()).with_field(Field::new("item", DataType::UInt32, true));
+    for row in rows {
+        builder.values().append_slice(value(row));
+        builder.append(true);
+    }
+    Arc::new(builder.finish())
+}
+
+pub fn hit_batch(rows: &[HitRow]) -> Result<RecordBatch, ExportError> {
+    let columns = vec![
+        strings(rows, |row| &row.player),
+        strings(rows, |row| &row.class),
+        Arc::new(UInt32Array::from_iter_values(rows.iter().map(|row| row.skill_id))) as ArrayRef,
+        strings(rows, |row| &row.skill_name),
+        integers(rows, |row| row.cast_timestamp),
+        integers(rows, |row| row.timestamp),
+        integers(rows, |row| row.damage),
+        booleans(rows, |row| row.crit),
+        booleans(rows, |row| row.back_attack),
+        booleans(rows, |row| row.front_attack),
+        id_lists(rows, |row| &row.buffed_by),
+        id_lists(rows, |row| &row.debuffed_by),
+        integers(rows, |row| row.rdps_damage_received),
+        integers(rows, |row| row.rdps_damage_received_support),
+    ];
+
+    Ok(RecordBatch::try_new(Arc::new(hit_schema()), columns)?)
+}
+
+pub fn skill_batch(rows: &[SkillRow]) -> Result<RecordBatch, ExportError> {
+    let columns = vec![
+        strings(rows, |row| &row.player),
+        strings(rows, |row| &row.class),
+        Arc::new(UInt32Array::from_iter_values(rows.iter().map(|row| row.skill_id))) as ArrayRef,
+        strings(rows, |row| &row.skill_name),
+        integers(rows, |row| row.total_damage),
+        integers(rows, |row| row.max_damage),
+        integers(rows, |row| row.casts),
+        integers(rows, |row| row.hits),
+        integers(rows, |row| row.crits),
+        integers(rows, |row| row.crit_damage),
+        integers(rows, |row| row.back_attacks),
+        integers(rows, |row| row.front_attacks),
+        integers(rows, |row| row.back_attack_damage),
+        integers(rows, |row| row.front_attack_damage),
+        integers(rows, |row| row.buffed_by_support),
+        integers(rows, |row| row.buffed_by_identity),
+        integers(rows, |row| row.debuffed_by_support),
+        integers(rows, |row| row.dps),
+    ];
+
+    Ok(RecordBatch::try_new(Arc::new(skill_schema()), columns)?)
+}
+
+fn write_batch<W: Write + Send>(batch: RecordBatch, writer: W) -> Result<(), ExportError> {
+    let mut writer = ArrowWriter::try_new(writer, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+pub fn write_hits_parquet<W: Write + Send>(encounter: &Encounter, writer: W) -> Result<(), ExportError> {
+    write_batch(hit_batch(&hit_rows(encounter))?, writer)
+}
+
+pub fn write_skills_parquet<W: Write + Send>(encounter: &Encounter, writer: W) -> Result<(), ExportError> {
+    write_batch(skill_batch(&skill_rows(encounter))?, writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Int64Type, UInt32Type};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use super::super::tests::encounter;
+    use super::*;
+
+    fn read(bytes: Vec<u8>) -> RecordBatch {
+        let path = std::env::temp_dir().join(format!("lost-metrics-core-{}.parquet", uuid::Uuid::new_v4()));
+        std::fs::write(&path, bytes).unwrap();
+        let file = std::fs::File::open(&path).unwrap();
+        let mut reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap().build().unwrap();
+        let batch = reader.next().unwrap().unwrap();
+        let _ = std::fs::remove_file(&path);
+        batch
+    }
+
+    #[test]
+    fn should_write_hits_parquet() {
+        let mut parquet = Vec::new();
+
+        write_hits_parquet(&encounter(), &mut parquet).unwrap();
+
+        let batch = read(parquet);
+        assert_eq!(batch.schema().as_ref(), &hit_schema());
+        assert_eq!(batch.num_rows(), 3);
+        let buffed_by = batch.column_by_name("buffed_by").unwrap().as_list::<i32>();
+        assert_eq!(
+            buffed_by.value(0).as_primitive::<UInt32Type>().values().as_ref(),
+            [211400, 362600]
+        );
+    }
+
+    #[test]
+    fn should_write_skills_parquet() {
+        let mut parquet = Vec::new();
+
+        write_skills_parquet(&encounter(), &mut parquet).unwrap();
+
+        let batch = read(parquet);
+        assert_eq!(batch.schema().as_ref(), &skill_schema());
+        let total_damage = batch.column_by_name("total_damage").unwrap().as_primitive::<Int64Type>();
+        assert_eq!(total_damage.values().as_ref(), [8000, 100]);
+    }
+}
@@ -0,0 +1,430 @@
+use hashbrown::HashMap;
+
+use crate::models::*;
+
+use super::GameData;
+
+const DAMAGE_BUFF_TYPES: [&str; 9] = [
+    "weaken_defense",
+    "weaken_resistance",
+    "skill_damage_amplify",
+    "beattacked_damage_amplify",
+    "skill_damage_amplify_attack",
+    "directional_attack_amplify",
+    "instant_stat_amplify",
+    "attack_power_amplify",
+    "instant_stat_amplify_by_contents",
+];
+
+const STAGGER_STATS: [&str; 3] = ["mastery", "mastery_x", "paralyzation_point_rate"];
+
+const COOLDOWN_STATS: [&str; 3] = ["rapidity", "rapidity_x", "cooldown_reduction"];
+
+const RESOURCE_STATS: [&str; 8] = [
+    "max_mp",
+    "max_mp_x",
+    "max_mp_rate",
+    "normal_mp_recovery",
+    "combat_mp_recovery",
+    "normal_mp_recovery_rate",
+    "combat_mp_recovery_rate",
+    "resource_recovery_rate",
+];
+
+const HP_STATS: [&str; 12] = [
+    "con",
+    "con_x",
+    "max_hp",
+    "max_hp_x",
+    "max_hp_rate",
+    "normal_hp_recovery",
+    "combat_hp_recovery",
+    "normal_hp_recovery_rate",
+    "combat_hp_recovery_rate",
+    "self_recovery_rate",
+    "drain_hp_dam_rate",
+    "vitality",
+];
+
+const DEFENSE_STATS: [&str; 12] = [
+    "def",
+    "res",
+    "def_x",
+    "res_x",
+    "def_x_x",
+    "res_x_x",
+    "def_pen_rate",
+    "res_pen_rate",
+    "physical_inc_rate",
+    "magical_inc_rate",
+    "endurance",
+    "endurance_x",
+];
+
+const MOVE_SPEED_STATS: [&str; 4] = [
+    "move_speed",
+    "move_speed_rate",
+    "vehicle_move_speed",
+    "vehicle_move_speed_rate",
+];
+
+const ATTACK_SPEED_STATS: [&str; 4] = ["attack_speed", "attack_speed_rate", "rapidity", "rapidity_x"];
+
+const CRIT_STATS: [&str; 3] = ["critical_hit_rate", "criticalhit", "criticalhit_x"];
+
+const DAMAGE_STATS: [&str; 28] = [
+    "attack_power_sub_rate_1",
+    "attack_power_sub_rate_2",
+    "skill_damage_sub_rate_1",
+    "skill_damage_sub_rate_2",
+    "fire_dam_rate",
+    "ice_dam_rate",
+    "electricity_dam_rate",
+    "earth_dam_rate",
+    "dark_dam_rate",
+    "holy_dam_rate",
+    "elements_dam_rate",
+    "str",
+    "agi",
+    "int",
+    "str_x",
+    "agi_x",
+    "int_x",
+    "char_attack_dam",
+    "attack_power_rate",
+    "skill_damage_rate",
+    "attack_power_rate_x",
+    "skill_damage_rate_x",
+    "hit_rate",
+    "dodge_rate",
+    "critical_dam_rate",
+    "awakening_dam_rate",
+    "attack_power_addend",
+    "weapon_dam",
+];
+
+const DAMAGE_OPTION_TYPES: [&str; 5] = [
+    "skill_damage",
+    "class_option",
+    "skill_group_damage",
+    "skill_critical_damage",
+    "skill_penetration",
+];
+
+const DAMAGE_COMBAT_EFFECT_ACTIONS: [&str; 8] = [
+    "modify_damage",
+    "modify_final_damage",
+    "modify_critical_multiplier",
+    "modify_penetration",
+    "modify_penetration_when_critical",
+    "modify_penetration_addend",
+    "modify_penetration_addend_when_critical",
+    "modify_damage_shield_multiplier",
+];
+
+/// Ether drops share the `ability` category with engravings and are told apart by unique group.
+const DROPS_OF_ETHER_UNIQUE_GROUPS: [u32; 5] = [501, 502, 503, 504, 505];
+
+/// Derives what a buff does from its type and passive options.
+pub fn classify_buff_type(
+    buff: &SkillBuffData,
+    combat_effects: &HashMap<i32, CombatEffectData>,
+) -> StatusEffectBuffTypeFlags {
+    let mut flags = StatusEffectBuffTypeFlags::NONE;
+    let buff_type = buff.buff_type.as_str();
+
+    if DAMAGE_BUFF_TYPES.contains(&buff_type) {
+        flags |= StatusEffectBuffTypeFlags::DMG;
+    } else if matches!(buff_type, "move_speed_down" | "all_speed_down") {
+        flags |= StatusEffectBuffTypeFlags::MOVESPEED;
+    } else if buff_type == "reset_cooldown" {
+        flags |= StatusEffectBuffTypeFlags::COOLDOWN;
+    } else if matches!(buff_type, "change_ai_point" | "ai_point_amplify") {
+        flags |= StatusEffectBuffTypeFlags::STAGGER;
+    } else if buff_type == "increase_identity_gauge" {
+        flags |= StatusEffectBuffTypeFlags::RESOURCE;
+    } else if buff_type == "shield" {
+        flags |= StatusEffectBuffTypeFlags::SHIELD;
+    }
+
+    // a positive buff or a negative debuff helps the party deal damage, the reverse is defensive
+    let offensive_or_defensive = |value: i32| {
+        if (buff.category == "buff" && value >= 0) || (buff.category == "debuff" && value <= 0) {
+            StatusEffectBuffTypeFlags::DMG
+        } else {
+            StatusEffectBuffTypeFlags::DEFENSE
+        }
+    };
+
+    for option in &buff.passive_options {
+        let key_stat = option.key_stat.as_str();
+
+        match option.option_type.as_str() {
+            "stat" => {
+                if STAGGER_STATS.contains(&key_stat) {
+                    flags |= StatusEffectBuffTypeFlags::STAGGER;
+                } else if COOLDOWN_STATS.contains(&key_stat) {
+                    flags |= StatusEffectBuffTypeFlags::COOLDOWN;
+                } else if RESOURCE_STATS.contains(&key_stat) {
+                    flags |= StatusEffectBuffTypeFlags::RESOURCE;
+                } else if HP_STATS.contains(&key_stat) {
+                    flags |= StatusEffectBuffTypeFlags::HP;
+                } else if DEFENSE_STATS.contains(&key_stat) {
+                    flags |= offensive_or_defensive(option.value);
+                } else if MOVE_SPEED_STATS.contains(&key_stat) {
+                    flags |= StatusEffectBuffTypeFlags::MOVESPEED;
+                }
+
+                if ATTACK_SPEED_STATS.contains(&key_stat) {
+                    flags |= StatusEffectBuffTypeFlags::ATKSPEED;
+                } else if CRIT_STATS.contains(&key_stat) {
+                    flags |= StatusEffectBuffTypeFlags::CRIT;
+                } else if DAMAGE_STATS.contains(&key_stat) {
+                    flags |= offensive_or_defensive(option.value);
+                }
+            }
+            "skill_critical_ratio" => flags |= StatusEffectBuffTypeFlags::CRIT,
+            option_type if DAMAGE_OPTION_TYPES.contains(&option_type) => {
+                flags |= offensive_or_defensive(option.value);
+            }
+            "skill_cooldown_reduction" | "arkgauge_cooldown_reduction" => {
+                flags |= StatusEffectBuffTypeFlags::COOLDOWN;
+            }
+            "skill_mana_reduction" | "mana_reduction" => {
+                flags |= StatusEffectBuffTypeFlags::RESOURCE;
+            }
+            "combat_effect" => {
+                let actions = combat_effects
+                    .get(&option.key_index)
+                    .into_iter()
+                    .flat_map(|combat_effect| &combat_effect.effects)
+                    .flat_map(|effect| &effect.actions);
+
+                for action in actions {
+                    let action_type = action.action_type.as_str();
+                    if DAMAGE_COMBAT_EFFECT_ACTIONS.contains(&action_type) {
+                        flags |= StatusEffectBuffTypeFlags::DMG;
+                    } else if action_type == "modify_critical_ratio" {
+                        flags |= StatusEffectBuffTypeFlags::CRIT;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    flags
+}
+
+impl GameData {
+    /// Builds the [`StatusEffect`] shown in `EncounterDamageStats::buffs`/`debuffs` for a buff id.
+    ///
+    /// Returns `None` for unknown buffs and those the game never displays.
+    /// `source_skill` picks the originating skill when the buff lists several.
+    pub fn classify_status_effect(&self, buff_id: u32, source_skill: Option<u32>) -> Option<StatusEffect> {
+        let buff = self.skill_buff(buff_id)?;
+
+        if buff.icon_show_type.as_deref() == Some("none") {
+            return None;
+        }
+
+        let buff_category = match buff.buff_category.as_deref().unwrap_or_default() {
+            "ability" if DROPS_OF_ETHER_UNIQUE_GROUPS.contains(&buff.unique_group) => "dropsofether",
+            buff_category => buff_category,
+        };
+
+        let target = match buff.target.as_str() {
+            "none" => StatusEffectTarget::OTHER,
+            "self" => StatusEffectTarget::SELF,
+            _ => StatusEffectTarget::PARTY,
+        };
+
+        let mut status_effect = StatusEffect {
+            target,
+            category: buff.category.clone(),
+            buff_category: buff_category.to_string(),
+            buff_type: classify_buff_type(buff, &self.combat_effects).bits(),
+            unique_group: buff.unique_group,
+            source: StatusEffectSource {
+                name: buff.name.clone().unwrap_or_default(),
+                desc: buff.desc.clone().unwrap_or_default(),
+                icon: buff.icon.clone().unwrap_or_default(),
+                ..Default::default()
+            },
+        };
+
+        match buff_category {
+            "classskill" | "arkpassive" | "identity" => {
+                status_effect.source.skill = self.buff_source_skill(buff_id, buff, source_skill).cloned();
+            }
+            "ability" if buff.unique_group != 0 => {
+                status_effect.source.skill = self.buff_source_skill(buff_id, buff, source_skill).cloned();
+            }
+            "set" => status_effect.source.set_name.clone_from(&buff.set_name),
+            "battleitem" => {
+                if let Some(item) = self.skill_effects.get(&buff_id) {
+                    if let Some(name) = &item.item_name {
+                        status_effect.source.name.clone_from(name);
+                    }
+                    if let Some(desc) = &item.item_desc {
+                        status_effect.source.desc.clone_from(desc);
+                    }
+                    if let Some(icon) = &item.icon {
+                        status_effect.source.icon.clone_from(icon);
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Some(status_effect)
+    }
+
+    /// Buff data rarely names its skill, so fall back to the id conventions used by the game data.
+    fn buff_source_skill(&self, buff_id: u32, buff: &SkillBuffData, source_skill: Option<u32>) -> Option<&SkillData> {
+        if let Some(source_skills) = &buff.source_skills {
+            let skill_id = source_skill
+                .filter(|skill_id| source_skills.contains(skill_id))
+                .or_else(|| source_skills.first().copied())?;
+            return self.skills.get(&skill_id);
+        }
+
+        [
+            buff_id / 10,
+            buff_id / 100 * 10,
+            buff.unique_group / 10,
+            buff.unique_group / 100 * 10,
+        ]
+        .into_iter()
+        .find_map(|skill_id| self.skills.get(&skill_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::meter::GameDataProvider;
+
+    use super::*;
+
+    fn stat(key_stat: &str, value: i32) -> PassiveOption {
+        PassiveOption {
+            option_type: "stat".to_string(),
+            key_stat: key_stat.to_string(),
+            key_index: 0,
+            value,
+        }
+    }
+
+    fn buff(id: i32, category: &str, buff_type: &str, passive_options: Vec<PassiveOption>) -> SkillBuffData {
+        SkillBuffData {
+            id,
+            category: category.to_string(),
+            buff_type: buff_type.to_string(),
+            buff_category: Some("classskill".to_string()),
+            target: "party".to_string(),
+            unique_group: id as u32,
+            passive_options,
+            ..Default::default()
+        }
+    }
+
+    fn support_game_data() -> GameData {
+        let heavenly_tune = buff(211601, "buff", "skill_damage_amplify", vec![stat("attack_power_rate", 1500)]);
+        let sound_shock = buff(
+            210230,
+            "debuff",
+            "instant_stat_amplify",
+            vec![PassiveOption {
+                option_type: "combat_effect".to_string(),
+                key_stat: String::new(),
+                key_index: 101,
+                value: 0,
+            }],
+        );
+        let mut sunsketch = buff(314004, "buff", "normal", vec![stat("criticalhit", 180)]);
+        sunsketch.source_skills = Some(vec![31400]);
+        let paladin_shield = buff(362200, "buff", "shield", vec![]);
+
+        let skill = |id: i32, class: Class| SkillData {
+            id,
+            class_id: class as u32,
+            ..Default::default()
+        };
+
+        GameData {
+            skills: HashMap::from([
+                (21160, skill(21160, Class::Bard)),
+                (21020, skill(21020, Class::Bard)),
+                (31400, skill(31400, Class::Artist)),
+            ]),
+            skill_buffs: [heavenly_tune, sound_shock, sunsketch, paladin_shield]
+                .into_iter()
+                .map(|buff| (buff.id as u32, buff))
+                .collect(),
+            combat_effects: HashMap::from([(
+                101,
+                CombatEffectData {
+                    effects: vec![CombatEffectDetail {
+                        actions: vec![CombatEffectAction {
+                            action_type: "modify_damage".to_string(),
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    }],
+                },
+            )]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn should_classify_known_support_buffs() {
+        let game_data = support_game_data();
+        let flags = |buff_id: u32| classify_buff_type(&game_data.skill_buffs[&buff_id], &game_data.combat_effects);
+
+        assert_eq!(flags(211601), StatusEffectBuffTypeFlags::DMG);
+        assert_eq!(flags(210230), StatusEffectBuffTypeFlags::DMG);
+        assert_eq!(flags(314004), StatusEffectBuffTypeFlags::CRIT);
+        assert_eq!(flags(362200), StatusEffectBuffTypeFlags::SHIELD);
+    }
+
+    #[test]
+    fn should_classify_stat_options() {
+        let combat_effects = HashMap::new();
+        let swiftness = buff(1, "buff", "normal", vec![stat("attack_speed_rate", 10), stat("move_speed_rate", 10)]);
+        let armor_break = buff(2, "debuff", "normal", vec![stat("def", -1200)]);
+        let stagger = buff(3, "buff", "normal", vec![stat("paralyzation_point_rate", 20)]);
+
+        assert_eq!(
+            classify_buff_type(&swiftness, &combat_effects),
+            StatusEffectBuffTypeFlags::ATKSPEED | StatusEffectBuffTypeFlags::MOVESPEED
+        );
+        assert_eq!(classify_buff_type(&armor_break, &combat_effects), StatusEffectBuffTypeFlags::DMG);
+        assert_eq!(classify_buff_type(&stagger, &combat_effects), StatusEffectBuffTypeFlags::STAGGER);
+    }
+
+    #[test]
+    fn should_build_status_effect_with_source_skill() {
+        let game_data = support_game_data();
+
+        let heavenly_tune = game_data.status_effect(211601).unwrap();
+        let sound_shock = game_data.status_effect(210230).unwrap();
+        let sunsketch = game_data.classify_status_effect(314004, Some(31400)).unwrap();
+
+        assert_eq!(heavenly_tune.target, StatusEffectTarget::PARTY);
+        assert_eq!(heavenly_tune.buff_category, "classskill");
+        assert_eq!(heavenly_tune.source.skill.unwrap().id, 21160);
+        assert_eq!(sound_shock.category, "debuff");
+        assert_eq!(sound_shock.source.skill.unwrap().id, 21020);
+        assert_eq!(sunsketch.source.skill.unwrap().class_id, Class::Artist as u32);
+        assert!(game_data.status_effect(1).is_none());
+    }
+
+    #[test]
+    fn should_hide_buffs_without_icon() {
+        let mut game_data = support_game_data();
+        game_data.skill_buffs.get_mut(&211601).unwrap().icon_show_type = Some("none".to_string());
+
+        assert!(game_data.status_effect(211601).is_none());
+    }
+}
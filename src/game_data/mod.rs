@@ -0,0 +1,215 @@
+//! Registry for the static meter-data files (`Skill.json`, `SkillBuff.json`, `Npc.json`, ...).
+
+mod buff_classifier;
+
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use hashbrown::HashMap;
+use log::warn;
+use serde::de::DeserializeOwned;
+
+use crate::meter::GameDataProvider;
+use crate::models::*;
+
+pub use buff_classifier::*;
+
+pub const SKILL_FILE: &str = "Skill.json";
+pub const SKILL_EFFECT_FILE: &str = "SkillEffect.json";
+pub const SKILL_BUFF_FILE: &str = "SkillBuff.json";
+pub const NPC_FILE: &str = "Npc.json";
+pub const ESTHER_FILE: &str = "Esther.json";
+pub const COMBAT_EFFECT_FILE: &str = "CombatEffect.json";
+
+#[derive(Debug)]
+pub enum GameDataError {
+    Io { path: PathBuf, source: io::Error },
+    Json { path: PathBuf, source: serde_json::Error },
+}
+
+impl Display for GameDataError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            GameDataError::Io { path, source } => write!(f, "failed to read {}: {}", path.display(), source),
+            GameDataError::Json { path, source } => write!(f, "failed to parse {}: {}", path.display(), source),
+        }
+    }
+}
+
+impl std::error::Error for GameDataError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GameDataError::Io { source, .. } => Some(source),
+            GameDataError::Json { source, .. } => Some(source),
+        }
+    }
+}
+
+/// Game data indexed by id.
+#[derive(Debug, Default, Clone)]
+pub struct GameData {
+    pub skills: HashMap<u32, SkillData>,
+    pub skill_effects: HashMap<u32, SkillEffectData>,
+    pub skill_buffs: HashMap<u32, SkillBuffData>,
+    pub npcs: HashMap<u32, Npc>,
+    pub esthers: Vec<Esther>,
+    pub combat_effects: HashMap<i32, CombatEffectData>,
+    esther_by_npc: HashMap<u32, usize>,
+}
+
+impl GameData {
+    /// Loads every known file from `dir`. Missing files are skipped with a warning,
+    /// malformed ones are reported as errors.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self, GameDataError> {
+        let dir = dir.as_ref();
+
+        let mut game_data = Self {
+            skills: load_file(dir, SKILL_FILE)?.unwrap_or_default(),
+            skill_effects: load_file(dir, SKILL_EFFECT_FILE)?.unwrap_or_default(),
+            skill_buffs: load_file(dir, SKILL_BUFF_FILE)?.unwrap_or_default(),
+            npcs: load_file(dir, NPC_FILE)?.unwrap_or_default(),
+            esthers: load_file(dir, ESTHER_FILE)?.unwrap_or_default(),
+            combat_effects: load_file(dir, COMBAT_EFFECT_FILE)?.unwrap_or_default(),
+            esther_by_npc: HashMap::new(),
+        };
+        game_data.reindex();
+
+        Ok(game_data)
+    }
+
+    /// Rebuilds derived indexes; call after mutating `esthers`.
+    pub fn reindex(&mut self) {
+        self.esther_by_npc = self
+            .esthers
+            .iter()
+            .enumerate()
+            .flat_map(|(index, esther)| esther.npc_ids.iter().map(move |npc_id| (*npc_id, index)))
+            .collect();
+    }
+
+    pub fn skill_name(&self, skill_id: u32) -> Option<&str> {
+        self.skills.get(&skill_id)?.name.as_deref()
+    }
+
+    pub fn skill_buff(&self, buff_id: u32) -> Option<&SkillBuffData> {
+        self.skill_buffs.get(&buff_id)
+    }
+
+    pub fn npc(&self, npc_id: u32) -> Option<&Npc> {
+        self.npcs.get(&npc_id)
+    }
+
+    pub fn is_boss(&self, npc_id: u32) -> bool {
+        self.npc(npc_id).is_some_and(Npc::is_boss)
+    }
+
+    pub fn esther_for_npc(&self, npc_id: u32) -> Option<&Esther> {
+        self.esther_by_npc
+            .get(&npc_id)
+            .and_then(|index| self.esthers.get(*index))
+    }
+
+    pub fn combat_effect(&self, id: i32) -> Option<&CombatEffectData> {
+        self.combat_effects.get(&id)
+    }
+}
+
+impl GameDataProvider for GameData {
+    fn skill(&self, skill_id: u32) -> Option<&SkillData> {
+        self.skills.get(&skill_id)
+    }
+
+    fn skill_effect(&self, skill_effect_id: u32) -> Option<&SkillEffectData> {
+        self.skill_effects.get(&skill_effect_id)
+    }
+
+    fn status_effect(&self, status_effect_id: u32) -> Option<StatusEffect> {
+        self.classify_status_effect(status_effect_id, None)
+    }
+
+    fn npc(&self, npc_id: u32) -> Option<&Npc> {
+        self.npcs.get(&npc_id)
+    }
+}
+
+fn load_file<T: DeserializeOwned>(dir: &Path, name: &str) -> Result<Option<T>, GameDataError> {
+    let path = dir.join(name);
+
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            warn!("{} not found, skipping", path.display());
+            return Ok(None);
+        }
+        Err(source) => return Err(GameDataError::Io { path, source }),
+    };
+
+    serde_json::from_str(&contents)
+        .map(Some)
+        .map_err(|source| GameDataError::Json { path, source })
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let path = std::env::temp_dir().join(format!("lost-metrics-core-{}", Uuid::new_v4()));
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn write(&self, name: &str, contents: &str) {
+            fs::write(self.0.join(name), contents).unwrap();
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn should_load_and_index_meter_data() {
+        let dir = TempDir::new();
+        dir.write(SKILL_FILE, r#"{
+            "16140": { "id": 16140, "name": "Bloody Rush", "type": "normal", "classId": 102, "icon": "bk_skill_01_14.png" }
+        }"#);
+        dir.write(NPC_FILE, r#"{
+            "480010": { "id": 480010, "name": "Thaemine the Lightqueller", "grade": "boss", "hpBars": 220, "type": "boss" },
+            "480011": { "id": 480011, "name": "Lightqueller Guard", "grade": "normal", "hpBars": 1, "type": "monster" }
+        }"#);
+        dir.write(ESTHER_FILE, r#"[
+            { "name": "Shandi", "icon": "esther_shandi.png", "skills": [94700], "npcs": [760020, 760021] }
+        ]"#);
+
+        let game_data = GameData::load(&dir.0).unwrap();
+
+        assert_eq!(game_data.skill_name(16140), Some("Bloody Rush"));
+        assert_eq!(game_data.skill_name(1), None);
+        assert!(game_data.is_boss(480010));
+        assert!(!game_data.is_boss(480011));
+        assert!(!game_data.is_boss(1));
+        assert_eq!(game_data.esther_for_npc(760021).unwrap().name, "Shandi");
+        assert!(game_data.esther_for_npc(480010).is_none());
+        assert!(game_data.skill_buffs.is_empty());
+    }
+
+    #[test]
+    fn should_report_malformed_file() {
+        let dir = TempDir::new();
+        dir.write(NPC_FILE, "{ not json");
+
+        let result = GameData::load(&dir.0);
+
+        assert!(matches!(result, Err(GameDataError::Json { .. })));
+    }
+}
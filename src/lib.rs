@@ -1 +1,10 @@
-pub mod models;
\ No newline at end of file
+pub mod archive;
+pub mod export;
+pub mod game_data;
+pub mod models;
+pub mod meter;
+pub mod prelude;
+pub mod schema;
+pub mod search;
+#[cfg(feature = "storage-sqlite")]
+pub mod storage;
@@ -0,0 +1,228 @@
+use hashbrown::HashMap;
+
+use crate::models::{BossHpLog, EncounterDamageStats, Npc};
+
+/// Records boss HP samples per boss name, one sample per second at most.
+#[derive(Debug, Default, Clone)]
+pub struct BossHpTracker {
+    logs: HashMap<String, Vec<BossHpLog>>,
+    /// Raid damage taken by each boss, summed per second.
+    damage: HashMap<String, Vec<(i32, i64)>>,
+    hp_bars: HashMap<String, u16>,
+}
+
+impl BossHpTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remembers how many HP bars the boss is displayed with.
+    pub fn register_boss(&mut self, name: impl Into<String>, npc: &Npc) {
+        self.hp_bars.insert(name.into(), npc.hp_bars);
+    }
+
+    /// Records the boss HP at `relative_timestamp` milliseconds after the fight started.
+    /// Samples within the same second replace each other.
+    pub fn record(&mut self, name: &str, relative_timestamp: i64, hp: i64, max_hp: i64) {
+        let hp = hp.max(0);
+        let percent = if max_hp > 0 { hp as f32 / max_hp as f32 } else { 0.0 };
+        let sample = BossHpLog::new((relative_timestamp / 1000) as i32, hp, percent);
+
+        let log = self.logs.entry_ref(name).or_default();
+        match log.last_mut() {
+            Some(last) if last.time == sample.time => *last = sample,
+            _ => log.push(sample),
+        }
+    }
+
+    /// Adds damage dealt to the boss at `relative_timestamp` milliseconds after the fight started.
+    pub fn record_damage(&mut self, name: &str, relative_timestamp: i64, damage: i64) {
+        let time = (relative_timestamp / 1000) as i32;
+
+        let log = self.damage.entry_ref(name).or_default();
+        match log.last_mut() {
+            Some((last, total)) if *last >= time => *total += damage,
+            _ => log.push((time, damage)),
+        }
+    }
+
+    pub fn log(&self, name: &str) -> Option<&[BossHpLog]> {
+        self.logs.get(name).map(Vec::as_slice)
+    }
+
+    /// HP bars left on the boss, e.g. `42.5` for a boss at 25% of 170 bars.
+    pub fn bars_remaining(&self, name: &str) -> Option<f32> {
+        let bars = *self.hp_bars.get(name)?;
+        let last = self.logs.get(name)?.last()?;
+        Some(last.p * bars as f32)
+    }
+
+    /// Projects the seconds until the boss dies from its remaining HP and the raid's damage
+    /// to it over the last `window` seconds. Returns `None` if it took no damage in that window.
+    pub fn time_until_kill(&self, name: &str, window: i32) -> Option<f64> {
+        let last = self.logs.get(name)?.last()?;
+
+        if last.hp == 0 {
+            return Some(0.0);
+        }
+
+        let damage = self.damage.get(name)?;
+        let window = window.max(1);
+        let end = damage.last()?.0.max(last.time);
+        let window_damage: i64 = damage
+            .iter()
+            .rev()
+            .take_while(|(time, _)| *time > end - window)
+            .map(|(_, damage)| damage)
+            .sum();
+
+        if window_damage <= 0 {
+            return None;
+        }
+
+        let raid_dps = window_damage as f64 / window as f64;
+        Some(last.hp as f64 / raid_dps)
+    }
+
+    /// Downsamples each log to at most `max_points` samples and stores them in `boss_hp_log`.
+    pub fn write_to(&self, stats: &mut EncounterDamageStats, max_points: usize) {
+        stats.boss_hp_log = self
+            .logs
+            .iter()
+            .map(|(name, log)| (name.clone(), downsample_hp_log(log, max_points)))
+            .collect();
+    }
+}
+
+/// Reduces `log` to at most `max_points` samples, always keeping the first and last one.
+///
+/// The log is split into equal time buckets and the lowest HP sample of each bucket is kept,
+/// so phase transitions and the kill remain visible.
+pub fn downsample_hp_log(log: &[BossHpLog], max_points: usize) -> Vec<BossHpLog> {
+    if log.len() <= max_points {
+        return log.to_vec();
+    }
+
+    if max_points < 2 {
+        return log[log.len() - max_points..].to_vec();
+    }
+
+    let first = &log[0];
+    let last = &log[log.len() - 1];
+    let inner = &log[1..log.len() - 1];
+    let buckets = max_points - 2;
+
+    let start = first.time as i64;
+    let span = (last.time as i64 - start).max(1);
+
+    let mut lowest: Vec<Option<&BossHpLog>> = vec![None; buckets];
+    for sample in inner {
+        let bucket = (((sample.time as i64 - start) * buckets as i64) / span).clamp(0, buckets as i64 - 1) as usize;
+        match lowest[bucket] {
+            Some(current) if current.hp <= sample.hp => {}
+            _ => lowest[bucket] = Some(sample),
+        }
+    }
+
+    let mut samples = Vec::with_capacity(max_points);
+    samples.push(first.clone());
+    samples.extend(lowest.into_iter().flatten().cloned());
+    samples.push(last.clone());
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOSS: &str = "Thaemine the Lightqueller";
+    const MAX_HP: i64 = 1_000_000;
+
+    /// Boss losing 1% of its HP every second.
+    fn linear_fight(seconds: i64) -> BossHpTracker {
+        let mut tracker = BossHpTracker::new();
+        for second in 0..=seconds {
+            if second > 0 {
+                tracker.record_damage(BOSS, second * 1000, 10_000);
+            }
+            tracker.record(BOSS, second * 1000, MAX_HP - second * 10_000, MAX_HP);
+        }
+        tracker
+    }
+
+    #[test]
+    fn should_keep_one_sample_per_second() {
+        let mut tracker = BossHpTracker::new();
+        tracker.record(BOSS, 100, 900, 1000);
+        tracker.record(BOSS, 900, 800, 1000);
+        tracker.record(BOSS, 1200, 700, 1000);
+        tracker.record(BOSS, 2500, 700, 1000);
+
+        let log = tracker.log(BOSS).unwrap();
+        assert_eq!(log.len(), 3);
+        assert_eq!((log[0].time, log[0].hp), (0, 800));
+        assert_eq!((log[1].time, log[1].hp), (1, 700));
+        assert_eq!((log[2].time, log[2].hp), (2, 700));
+        assert_eq!(log[1].p, 0.7);
+    }
+
+    #[test]
+    fn should_project_time_until_kill() {
+        let tracker = linear_fight(30);
+
+        assert_eq!(tracker.time_until_kill(BOSS, 10), Some(70.0));
+        assert_eq!(tracker.time_until_kill("Unknown", 10), None);
+    }
+
+    #[test]
+    fn should_project_from_recent_window_only() {
+        let mut tracker = linear_fight(10);
+        // damage slows to 0.5% per second after 10 seconds
+        for second in 11..=30 {
+            tracker.record_damage(BOSS, second * 1000, 5_000);
+            tracker.record(BOSS, second * 1000, 900_000 - (second - 10) * 5_000, MAX_HP);
+        }
+
+        assert_eq!(tracker.time_until_kill(BOSS, 10), Some(160.0));
+    }
+
+    #[test]
+    fn should_project_from_raid_damage_not_hp_loss() {
+        let mut tracker = linear_fight(10);
+        // the boss heals back while the raid keeps dealing 1% per second
+        tracker.record_damage(BOSS, 11_000, 10_000);
+        tracker.record(BOSS, 11_000, 950_000, MAX_HP);
+
+        assert_eq!(tracker.time_until_kill(BOSS, 10), Some(95.0));
+        assert_eq!(BossHpTracker::new().time_until_kill(BOSS, 10), None);
+    }
+
+    #[test]
+    fn should_report_bars_remaining() {
+        let mut tracker = linear_fight(25);
+        let npc = Npc {
+            hp_bars: 200,
+            ..Default::default()
+        };
+        tracker.register_boss(BOSS, &npc);
+
+        assert_eq!(tracker.bars_remaining(BOSS), Some(150.0));
+    }
+
+    #[test]
+    fn should_downsample_keeping_endpoints_and_drops() {
+        let mut tracker = linear_fight(100);
+        // short phase transition dip which must survive downsampling
+        tracker.logs.get_mut(BOSS).unwrap()[51].hp = 100;
+
+        let mut stats = EncounterDamageStats::default();
+        tracker.write_to(&mut stats, 12);
+        let log = &stats.boss_hp_log[BOSS];
+
+        assert_eq!(log.len(), 12);
+        assert_eq!(log.first().unwrap().time, 0);
+        assert_eq!(log.last().unwrap().time, 100);
+        assert!(log.iter().any(|sample| sample.hp == 100));
+        assert!(log.windows(2).all(|pair| pair[0].time < pair[1].time));
+    }
+}
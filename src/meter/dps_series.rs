@@ -0,0 +1,235 @@
+use crate::models::{merge_intervals, Encounter, EncounterEntity, SkillHit};
+
+pub const DEFAULT_ROLLING_WINDOW: usize = 10;
+
+/// Per-second DPS series of one entity.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DpsSeries {
+    /// Damage dealt so far divided by the elapsed seconds, one value per second.
+    pub average: Vec<i64>,
+    /// Damage of the last `rolling_window` seconds divided by the window, one value per second.
+    /// The window is shorter for the first seconds of the fight.
+    pub rolling: Vec<i64>,
+}
+
+/// Builds [`DpsSeries`] from hit logs.
+///
+/// Paused spans (intermissions, `pause_session`) are cut out of the timeline so they do not
+/// drag the averages down, and the series of a dead player ends at its death.
+#[derive(Debug, Clone)]
+pub struct DpsSeriesBuilder {
+    rolling_window: usize,
+    pauses: Vec<(i64, i64)>,
+}
+
+impl Default for DpsSeriesBuilder {
+    fn default() -> Self {
+        Self {
+            rolling_window: DEFAULT_ROLLING_WINDOW,
+            pauses: Vec::new(),
+        }
+    }
+}
+
+impl DpsSeriesBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Width of the rolling window in seconds.
+    pub fn rolling_window(mut self, seconds: usize) -> Self {
+        self.rolling_window = seconds.max(1);
+        self
+    }
+
+    /// Excludes `[start, end)`, in milliseconds relative to the fight start, from the timeline.
+    /// Overlapping or adjacent pauses are merged.
+    pub fn pause(mut self, start: i64, end: i64) -> Self {
+        self.pauses.push((start, end));
+        self.pauses = merge_intervals(self.pauses);
+        self
+    }
+
+    /// Maps a relative timestamp onto the timeline without pauses.
+    fn active_time(&self, timestamp: i64) -> i64 {
+        let paused: i64 = self
+            .pauses
+            .iter()
+            .map(|&(start, end)| (timestamp.min(end) - start).max(0))
+            .sum();
+        (timestamp - paused).max(0)
+    }
+
+    /// Builds the series for hits within the first `duration` milliseconds.
+    /// `end` cuts the series short, e.g. at the relative time of death.
+    pub fn build<'a>(&self, hits: impl IntoIterator<Item = &'a SkillHit>, duration: i64, end: Option<i64>) -> DpsSeries {
+        let end = end.map_or(duration, |end| end.clamp(0, duration));
+        let active_end = self.active_time(end);
+        if active_end <= 0 {
+            return DpsSeries::default();
+        }
+
+        let seconds = ((active_end + 999) / 1000) as usize;
+        let mut damage = vec![0i64; seconds];
+
+        for hit in hits {
+            if hit.timestamp < 0 || hit.timestamp > end {
+                continue;
+            }
+
+            let second = (self.active_time(hit.timestamp) / 1000) as usize;
+            damage[second.min(seconds - 1)] += hit.damage;
+        }
+
+        let mut cumulative = 0;
+        let average = damage
+            .iter()
+            .enumerate()
+            .map(|(index, damage)| {
+                cumulative += damage;
+                cumulative / (index as i64 + 1)
+            })
+            .collect();
+
+        let mut window_damage = 0;
+        let rolling = (0..seconds)
+            .map(|index| {
+                window_damage += damage[index];
+                if index >= self.rolling_window {
+                    window_damage -= damage[index - self.rolling_window];
+                }
+                window_damage / (index + 1).min(self.rolling_window) as i64
+            })
+            .collect();
+
+        DpsSeries { average, rolling }
+    }
+
+    pub fn build_for_entity(&self, entity: &EncounterEntity, fight_start: i64, duration: i64) -> DpsSeries {
+        let hits = entity
+            .skills
+            .values()
+            .flat_map(|skill| &skill.skill_cast_log)
+            .flat_map(|cast| &cast.hits);
+
+        let death = (entity.is_dead && entity.damage_stats.death_time > fight_start)
+            .then(|| entity.damage_stats.death_time - fight_start);
+
+        self.build(hits, duration, death)
+    }
+
+    /// Fills `dps_average` and `dps_rolling_10s_avg` of every entity which dealt damage.
+    pub fn apply(&self, encounter: &mut Encounter) {
+        let duration = encounter.last_combat_packet - encounter.fight_start;
+        let fight_start = encounter.fight_start;
+
+        for entity in encounter.entities.values_mut() {
+            if entity.damage_stats.damage_dealt == 0 {
+                continue;
+            }
+
+            let series = self.build_for_entity(entity, fight_start, duration);
+            entity.damage_stats.dps_average = series.average;
+            entity.damage_stats.dps_rolling_10s_avg = series.rolling;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::*;
+
+    use super::*;
+
+    fn hits(hits: &[(i64, i64)]) -> Vec<SkillHit> {
+        hits.iter()
+            .map(|&(timestamp, damage)| SkillHit {
+                timestamp,
+                damage,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_build_average_and_rolling_series() {
+        let hits = hits(&[(0, 100), (500, 100), (1500, 400), (3999, 300)]);
+
+        let series = DpsSeriesBuilder::new().rolling_window(2).build(&hits, 4000, None);
+
+        assert_eq!(series.average, [200, 300, 200, 225]);
+        assert_eq!(series.rolling, [200, 300, 200, 150]);
+    }
+
+    #[test]
+    fn should_use_ten_second_window_by_default() {
+        let hits = hits(&(0..12).map(|second| (second * 1000, 1000)).collect::<Vec<_>>());
+
+        let series = DpsSeriesBuilder::new().build(&hits, 12_000, None);
+
+        assert_eq!(series.rolling, [1000; 12]);
+        assert_eq!(series.average, [1000; 12]);
+    }
+
+    #[test]
+    fn should_cut_pauses_out_of_timeline() {
+        let hits = hits(&[(0, 1000), (1000, 1000), (10_000, 1000)]);
+
+        let series = DpsSeriesBuilder::new().pause(2000, 10_000).build(&hits, 11_000, None);
+
+        assert_eq!(series.average, [1000, 1000, 1000]);
+    }
+
+    #[test]
+    fn should_merge_overlapping_and_nested_pauses() {
+        let hits = hits(&(0..10).map(|index| (index * 1000, 100)).collect::<Vec<_>>());
+        let expected = DpsSeriesBuilder::new().pause(1000, 5000).build(&hits, 10_000, None);
+
+        let nested = DpsSeriesBuilder::new()
+            .pause(1000, 5000)
+            .pause(2000, 4000)
+            .build(&hits, 10_000, None);
+        let overlapping = DpsSeriesBuilder::new()
+            .pause(3000, 5000)
+            .pause(1000, 3000)
+            .pause(2000, 4500)
+            .build(&hits, 10_000, None);
+
+        assert_eq!(expected.average.len(), 6);
+        assert_eq!(nested, expected);
+        assert_eq!(overlapping, expected);
+    }
+
+    #[test]
+    fn should_end_series_at_death() {
+        let skill = Skill {
+            skill_cast_log: vec![SkillCast {
+                hits: hits(&[(0, 600), (2000, 600), (5000, 600)]),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let entity = EncounterEntity {
+            is_dead: true,
+            skills: [(1, skill)].into_iter().collect(),
+            damage_stats: DamageStats {
+                damage_dealt: 1800,
+                death_time: 1_000_003_000,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut encounter = Encounter {
+            fight_start: 1_000_000_000,
+            last_combat_packet: 1_000_006_000,
+            entities: [("Alice".to_string(), entity)].into_iter().collect(),
+            ..Default::default()
+        };
+
+        DpsSeriesBuilder::new().apply(&mut encounter);
+
+        let stats = &encounter.entities["Alice"].damage_stats;
+        assert_eq!(stats.dps_average, [600, 300, 400]);
+        assert_eq!(stats.dps_rolling_10s_avg, [600, 300, 400]);
+    }
+}
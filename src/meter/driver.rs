@@ -0,0 +1,539 @@
+use chrono::{DateTime, Utc};
+
+use crate::models::*;
+
+use super::*;
+
+/// Samples kept per boss in `boss_hp_log` when finishing an encounter.
+pub const BOSS_HP_LOG_POINTS: usize = 500;
+
+/// Feeds [`MeterEvent`]s into an [`EncounterState`] and the trackers derived from it.
+pub struct MeterDriver {
+    pub state: EncounterState,
+    entities: EntityTracker,
+    parties: PartyTracker,
+    status_effects: StatusEffectRegistry,
+    boss_hp: BossHpTracker,
+    stagger: StaggerTracker,
+    identity_max_gauge: Option<u32>,
+    identity: Option<IdentityTracker>,
+}
+
+impl MeterDriver {
+    pub fn new(state: EncounterState) -> Self {
+        Self {
+            state,
+            entities: EntityTracker::new(),
+            parties: PartyTracker::new(),
+            status_effects: StatusEffectRegistry::new(),
+            boss_hp: BossHpTracker::new(),
+            stagger: StaggerTracker::new(),
+            identity_max_gauge: None,
+            identity: None,
+        }
+    }
+
+    /// Records identity gauges of the local player, `max_gauge` being a full `gauge1`.
+    pub fn track_identity(mut self, max_gauge: u32) -> Self {
+        self.identity_max_gauge = Some(max_gauge);
+        self
+    }
+
+    pub fn encounter(&self) -> &Encounter {
+        &self.state.encounter
+    }
+
+    pub fn entity(&self, id: u64) -> Option<&Entity> {
+        self.entities.get(id)
+    }
+
+    pub fn entities(&self) -> &EntityTracker {
+        &self.entities
+    }
+
+    pub fn parties(&self) -> &PartyTracker {
+        &self.parties
+    }
+
+    pub fn boss_hp(&self) -> &BossHpTracker {
+        &self.boss_hp
+    }
+
+    pub fn status_effects(&self) -> &StatusEffectRegistry {
+        &self.status_effects
+    }
+
+    pub fn run<'a>(&mut self, events: impl IntoIterator<Item = &'a MeterEvent>) {
+        for event in events {
+            self.handle(event);
+        }
+    }
+
+    pub fn handle(&mut self, event: &MeterEvent) {
+        match event {
+            MeterEvent::NewPlayer(entity) => {
+                if entity.is_local_player && self.identity.is_none() {
+                    self.identity = self
+                        .identity_max_gauge
+                        .map(|max_gauge| IdentityTracker::new(entity.class_id, max_gauge));
+                }
+                self.state.on_new_entity(entity);
+                self.parties.on_player(entity);
+                self.entities.spawn(entity.clone());
+            }
+            MeterEvent::NewNpc(entity) | MeterEvent::NewSummon(entity) => {
+                if entity.entity_type == EntityType::Boss {
+                    if let Some(npc) = self.state.game_data().npc(entity.npc_id) {
+                        self.boss_hp.register_boss(entity.name.clone(), npc);
+                    }
+                }
+                self.entities.spawn(entity.clone());
+            }
+            MeterEvent::Despawn { entity_id, .. } => {
+                self.entities.despawn(*entity_id);
+            }
+            MeterEvent::SkillStart {
+                source_id,
+                skill_id,
+                timestamp,
+            } => {
+                if let Some(owner) = self.entities.owner(*source_id) {
+                    self.state.on_skill_start(owner, *skill_id, *timestamp);
+                }
+            }
+            MeterEvent::Damage(record) => self.on_damage(record),
+            MeterEvent::StatusEffectAdd(effect) => {
+                let timestamp = effect.timestamp.timestamp_millis();
+                if let Some(entity) = self.status_effect_target(effect.target_id, effect.target_type) {
+                    entity.on_crowd_control(effect, timestamp);
+                }
+                self.status_effects.add(effect.clone());
+            }
+            MeterEvent::StatusEffectRemove {
+                target_id,
+                target_type,
+                instance_id,
+                timestamp,
+            } => {
+                let removed = self.status_effects.remove(*target_id, *target_type, *instance_id);
+                if let Some(effect) = removed.filter(|effect| effect.status_effect_type == StatusEffectType::HardCrowdControl) {
+                    if let Some(entity) = self.status_effect_target(*target_id, *target_type) {
+                        entity.shorten_crowd_control(effect.timestamp.timestamp_millis(), *timestamp);
+                    }
+                }
+            }
+            MeterEvent::StatusEffectUpdate {
+                target_id,
+                target_type,
+                instance_id,
+                change,
+                timestamp,
+            } => match *change {
+                StatusEffectChange::Value(value) => {
+                    self.status_effects.update_value(*target_id, *target_type, *instance_id, value);
+                }
+                StatusEffectChange::StackCount(stack_count) => {
+                    self.status_effects
+                        .update_stack_count(*target_id, *target_type, *instance_id, stack_count);
+                }
+                StatusEffectChange::Refresh {
+                    expiration_delay,
+                    end_tick,
+                } => {
+                    self.status_effects.refresh(
+                        *target_id,
+                        *target_type,
+                        *instance_id,
+                        to_date_time(*timestamp),
+                        expiration_delay,
+                        end_tick,
+                    );
+                }
+            },
+            MeterEvent::Stagger { stagger, timestamp } => {
+                let relative_timestamp = self.relative_timestamp(*timestamp);
+                self.stagger.on_stagger(stagger, relative_timestamp);
+            }
+            MeterEvent::Identity { identity, timestamp } => {
+                let relative_timestamp = self.relative_timestamp(*timestamp);
+                if let Some(tracker) = &mut self.identity {
+                    tracker.on_identity(identity, relative_timestamp);
+                }
+            }
+            MeterEvent::Death { target_id, timestamp } => self.on_death(*target_id, *timestamp),
+            MeterEvent::ZoneChange { .. } => {
+                self.entities.clear();
+                self.status_effects.clear();
+            }
+            MeterEvent::RaidResult { cleared, .. } => {
+                let encounter = &mut self.state.encounter;
+                encounter.cleared = *cleared;
+                encounter.encounter_damage_stats.misc.get_or_insert_with(Default::default).raid_clear = Some(*cleared);
+            }
+            MeterEvent::PartyInfo {
+                party_instance_id,
+                raid_instance_id,
+                members,
+                ..
+            } => self.parties.on_party_info(*party_instance_id, *raid_instance_id, members),
+            MeterEvent::PartyLeave {
+                party_instance_id,
+                character_id,
+                ..
+            } => self.parties.on_party_leave(*party_instance_id, *character_id),
+        }
+    }
+
+    /// Computes dps and writes the tracker summaries into the encounter.
+    pub fn finish(&mut self) -> &Encounter {
+        self.state.update_dps();
+
+        let encounter = &mut self.state.encounter;
+        let duration = encounter.duration;
+        let stats = &mut encounter.encounter_damage_stats;
+        self.stagger.write_to(stats, duration);
+        self.boss_hp.write_to(stats, BOSS_HP_LOG_POINTS);
+        self.parties.write_to(encounter);
+
+        if let Some(tracker) = &self.identity {
+            if let Some(local_player) = encounter.entities.get_mut(&encounter.local_player) {
+                tracker.write_to(&mut local_player.skill_stats, duration);
+            }
+        }
+
+        DpsSeriesBuilder::new().apply(encounter);
+
+        &self.state.encounter
+    }
+
+    fn on_damage(&mut self, record: &DamageRecord) {
+        let (Some(source), Some(owner)) = (self.entities.get(record.source_id), self.entities.owner(record.source_id)) else {
+            return;
+        };
+        let target = self.entities.get(record.target_id).cloned().unwrap_or_else(|| Entity {
+            id: record.target_id,
+            ..Default::default()
+        });
+
+        let at = to_date_time(record.timestamp);
+        let mut se_on_source = self.status_effects.effects_on(owner.id, StatusEffectTargetType::Local, at);
+        if owner.character_id != 0 {
+            se_on_source.extend(
+                self.status_effects
+                    .effects_on(owner.character_id, StatusEffectTargetType::Party, at),
+            );
+        }
+        let se_on_target = self.status_effects.effects_on(target.id, StatusEffectTargetType::Local, at);
+
+        let event = DamageEvent {
+            is_valid: true,
+            is_battle_item: record.is_battle_item,
+            hit_flag: record.hit_flag,
+            hit_option: record.hit_option,
+            skill_id: record.skill_id,
+            skill_effect_id: record.skill_effect_id,
+            damage: record.damage,
+            target_current_hp: record.target_current_hp,
+            target_max_hp: record.target_max_hp,
+            owner_entity: owner,
+            source_entity: source,
+            se_on_source_ids: se_on_source.iter().map(|effect| effect.status_effect_id).collect(),
+            se_on_source,
+            se_on_target_ids: se_on_target.iter().map(|effect| effect.status_effect_id).collect(),
+            se_on_target,
+            target_entity: target,
+            timestamp: record.timestamp,
+        };
+
+        self.state.on_damage(&event);
+
+        if event.target_entity.entity_type == EntityType::Boss {
+            let relative_timestamp = record.timestamp - self.state.encounter.fight_start;
+            self.boss_hp
+                .record_damage(&event.target_entity.name, relative_timestamp, record.damage);
+            self.boss_hp.record(
+                &event.target_entity.name,
+                relative_timestamp,
+                record.target_current_hp,
+                record.target_max_hp,
+            );
+        }
+    }
+
+    fn on_death(&mut self, target_id: u64, timestamp: i64) {
+        let Some(entity) = self.entities.get(target_id) else {
+            return;
+        };
+
+        if let Some(dead) = self.state.encounter.entities.get_mut(&entity.name) {
+            dead.is_dead = true;
+            dead.current_hp = 0;
+            dead.damage_stats.deaths += 1;
+            dead.damage_stats.death_time = timestamp;
+            dead.cap_incapacitation_durations_to_death_time();
+        }
+
+        self.status_effects.clear_target(target_id, StatusEffectTargetType::Local);
+    }
+
+    /// Encounter entity a status effect applies to: party effects target character ids, local ones entity ids.
+    fn status_effect_target(&mut self, target_id: u64, target_type: StatusEffectTargetType) -> Option<&mut EncounterEntity> {
+        let entity = match target_type {
+            StatusEffectTargetType::Party => self.entities.by_character_id(target_id),
+            StatusEffectTargetType::Local => self.entities.get(target_id),
+        }?;
+
+        self.state.encounter.entities.get_mut(&entity.name)
+    }
+
+    fn relative_timestamp(&self, timestamp: i64) -> i64 {
+        let fight_start = self.state.encounter.fight_start;
+        if fight_start == 0 { 0 } else { timestamp - fight_start }
+    }
+}
+
+fn to_date_time(timestamp: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp_millis(timestamp).unwrap_or_default()
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    const START: i64 = 1_700_000_000_000;
+
+    fn player(id: u64, name: &str, is_local_player: bool) -> Entity {
+        Entity {
+            id,
+            name: name.to_string(),
+            entity_type: EntityType::Player,
+            class_id: Class::Berserker,
+            character_id: id * 100,
+            is_local_player,
+            ..Default::default()
+        }
+    }
+
+    fn effect(
+        instance_id: u32,
+        status_effect_id: u32,
+        target_id: u64,
+        target_type: StatusEffectTargetType,
+        status_effect_type: StatusEffectType,
+        at: i64,
+    ) -> StatusEffectDetails {
+        StatusEffectDetails {
+            instance_id,
+            status_effect_id,
+            target_id,
+            target_type,
+            status_effect_type,
+            expiration_delay: 10.0,
+            timestamp: to_date_time(at),
+            ..Default::default()
+        }
+    }
+
+    fn damage(source_id: u64, skill_id: u32, damage: i64, hp: i64, at: i64) -> MeterEvent {
+        MeterEvent::Damage(DamageRecord {
+            source_id,
+            target_id: 9,
+            skill_id,
+            damage,
+            target_current_hp: hp,
+            target_max_hp: 10_000,
+            timestamp: at,
+            ..Default::default()
+        })
+    }
+
+    pub(crate) fn scripted_events() -> Vec<MeterEvent> {
+        vec![
+            MeterEvent::NewPlayer(player(1, "Alice", true)),
+            MeterEvent::NewPlayer(player(2, "Bob", false)),
+            MeterEvent::NewSummon(Entity {
+                id: 5,
+                entity_type: EntityType::Summon,
+                owner_id: 1,
+                ..Default::default()
+            }),
+            MeterEvent::NewNpc(Entity {
+                id: 9,
+                name: "Thaemine".to_string(),
+                entity_type: EntityType::Boss,
+                ..Default::default()
+            }),
+            MeterEvent::SkillStart {
+                source_id: 1,
+                skill_id: 16140,
+                timestamp: START,
+            },
+            damage(1, 16140, 1000, 9000, START),
+            MeterEvent::StatusEffectAdd(effect(
+                1,
+                211400,
+                100,
+                StatusEffectTargetType::Party,
+                StatusEffectType::Other,
+                START + 500,
+            )),
+            damage(5, 16300, 500, 8500, START + 1000),
+            MeterEvent::Stagger {
+                stagger: Stagger { current: 1000, max: 1000 },
+                timestamp: START + 1500,
+            },
+            MeterEvent::StatusEffectAdd(effect(
+                2,
+                480001,
+                2,
+                StatusEffectTargetType::Local,
+                StatusEffectType::HardCrowdControl,
+                START + 2000,
+            )),
+            MeterEvent::StatusEffectRemove {
+                target_id: 2,
+                target_type: StatusEffectTargetType::Local,
+                instance_id: 2,
+                timestamp: START + 3000,
+            },
+            damage(2, 21070, 2000, 6500, START + 3000),
+            MeterEvent::Death {
+                target_id: 2,
+                timestamp: START + 3500,
+            },
+            damage(1, 16140, 6500, 0, START + 4000),
+            MeterEvent::PartyInfo {
+                party_instance_id: 7,
+                raid_instance_id: 3,
+                members: vec![
+                    PartyMember {
+                        character_id: 100,
+                        name: "Alice".to_string(),
+                    },
+                    PartyMember {
+                        character_id: 200,
+                        name: String::new(),
+                    },
+                ],
+                timestamp: START + 4000,
+            },
+            MeterEvent::RaidResult {
+                cleared: true,
+                timestamp: START + 4500,
+            },
+        ]
+    }
+
+    #[test]
+    fn should_replay_scripted_encounter() {
+        let mut driver = MeterDriver::new(EncounterState::default());
+
+        driver.run(&scripted_events());
+        let encounter = driver.finish();
+
+        assert_eq!(encounter.local_player, "Alice");
+        assert_eq!(encounter.fight_start, START);
+        assert_eq!(encounter.duration, 4000);
+        assert!(encounter.cleared);
+        assert_eq!(encounter.current_boss_name, "Thaemine");
+        assert_eq!(encounter.encounter_damage_stats.misc.as_ref().unwrap().party_info.as_ref().unwrap()[&0], ["Alice", "Bob"]);
+
+        let alice = &encounter.entities["Alice"];
+        assert_eq!(alice.damage_stats.damage_dealt, 8000);
+        assert_eq!(alice.skills[&16300].total_damage, 500);
+        assert_eq!(alice.skills[&16140].skill_cast_log[0].hits.len(), 2);
+        assert_eq!(alice.skills[&16140].skill_cast_log[0].hits[0].buffed_by, Vec::<u32>::new());
+        assert_eq!(alice.skills[&16140].skill_cast_log[0].hits[1].buffed_by, [211400]);
+        assert_eq!(alice.damage_stats.dps_average.len(), 4);
+
+        let bob = &encounter.entities["Bob"];
+        assert!(bob.is_dead);
+        assert_eq!(bob.damage_stats.death_time, START + 3500);
+        assert_eq!(bob.damage_stats.incapacitations[0].duration, 1000);
+
+        let stats = &encounter.encounter_damage_stats;
+        assert_eq!(stats.total_damage_dealt, 10_000);
+        assert_eq!(stats.boss_hp_log["Thaemine"].last().unwrap().hp, 0);
+        assert_eq!(stats.max_stagger, 1000);
+        assert_eq!(stats.misc.as_ref().unwrap().raid_clear, Some(true));
+    }
+
+    #[test]
+    fn should_register_bosses_with_their_hp_bars() {
+        struct BossData(Npc);
+
+        impl GameDataProvider for BossData {
+            fn npc(&self, npc_id: u32) -> Option<&Npc> {
+                (npc_id == self.0.id as u32).then_some(&self.0)
+            }
+        }
+
+        let npc = Npc {
+            hp_bars: 160,
+            ..Default::default()
+        };
+        let state = EncounterState::builder().game_data(Arc::new(BossData(npc))).build();
+        let mut driver = MeterDriver::new(state);
+        let events = scripted_events();
+
+        driver.run(&events[..6]);
+
+        assert_eq!(driver.boss_hp().bars_remaining("Thaemine"), Some(144.0));
+        assert_eq!(driver.boss_hp().time_until_kill("Thaemine", 10), Some(90.0));
+    }
+
+    #[test]
+    fn should_record_identity_of_local_player() {
+        let mut driver = MeterDriver::new(EncounterState::default()).track_identity(1000);
+        let mut events = scripted_events();
+        events.push(MeterEvent::Identity {
+            identity: Identity {
+                gauge1: 500,
+                ..Default::default()
+            },
+            timestamp: START + 1000,
+        });
+
+        driver.run(&events);
+        let encounter = driver.finish();
+
+        assert!(encounter.entities["Alice"].skill_stats.identity_stats.is_some());
+        assert!(encounter.entities["Bob"].skill_stats.identity_stats.is_none());
+    }
+
+    #[test]
+    fn should_forget_entities_on_zone_change() {
+        let mut driver = MeterDriver::new(EncounterState::default());
+        driver.run(&scripted_events()[..5]);
+
+        driver.handle(&MeterEvent::ZoneChange { timestamp: START });
+        driver.handle(&damage(1, 16140, 1000, 9000, START));
+
+        assert!(driver.entity(1).is_none());
+        assert_eq!(driver.encounter().entities["Alice"].damage_stats.damage_dealt, 0);
+    }
+
+    #[test]
+    fn should_credit_nested_projectiles_and_ignore_despawned_sources() {
+        let mut driver = MeterDriver::new(EncounterState::default());
+        driver.run(&scripted_events()[..5]);
+
+        driver.handle(&MeterEvent::NewSummon(Entity {
+            id: 6,
+            entity_type: EntityType::Projectile,
+            owner_id: 5,
+            ..Default::default()
+        }));
+        driver.handle(&damage(6, 16140, 1000, 9000, START));
+        driver.handle(&MeterEvent::Despawn {
+            entity_id: 6,
+            timestamp: START + 500,
+        });
+        driver.handle(&damage(6, 16140, 1000, 8000, START + 1000));
+
+        assert_eq!(driver.entities().owning_player(5).unwrap().name, "Alice");
+        assert_eq!(driver.encounter().entities["Alice"].damage_stats.damage_dealt, 1000);
+    }
+}
@@ -0,0 +1,688 @@
+use std::sync::Arc;
+
+use crate::models::*;
+
+use super::GameDataProvider;
+
+/// Buff ids of the support hats, attributed separately from regular support buffs.
+pub const HAT_BUFF_IDS: [u32; 3] = [362600, 212305, 319503];
+
+pub fn is_hat_buff(buff_id: u32) -> bool {
+    HAT_BUFF_IDS.contains(&buff_id)
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct BuffAttribution {
+    buffed_by_support: bool,
+    buffed_by_identity: bool,
+    buffed_by_hat: bool,
+    debuffed_by_support: bool,
+}
+
+/// Configures and creates an [`EncounterState`].
+pub struct EncounterBuilder {
+    local_player: String,
+    difficulty: Option<String>,
+    boss_only_damage: bool,
+    game_data: Arc<dyn GameDataProvider + Send + Sync>,
+}
+
+impl Default for EncounterBuilder {
+    fn default() -> Self {
+        Self {
+            local_player: String::new(),
+            difficulty: None,
+            boss_only_damage: false,
+            game_data: Arc::new(()),
+        }
+    }
+}
+
+impl EncounterBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn local_player(mut self, name: impl Into<String>) -> Self {
+        self.local_player = name.into();
+        self
+    }
+
+    pub fn difficulty(mut self, difficulty: impl Into<String>) -> Self {
+        self.difficulty = Some(difficulty.into());
+        self
+    }
+
+    /// When enabled, player damage is only recorded against [`EntityType::Boss`] targets.
+    pub fn boss_only_damage(mut self, enabled: bool) -> Self {
+        self.boss_only_damage = enabled;
+        self
+    }
+
+    pub fn game_data(mut self, game_data: Arc<dyn GameDataProvider + Send + Sync>) -> Self {
+        self.game_data = game_data;
+        self
+    }
+
+    pub fn build(self) -> EncounterState {
+        EncounterState {
+            encounter: Encounter {
+                local_player: self.local_player,
+                difficulty: self.difficulty,
+                boss_only_damage: self.boss_only_damage,
+                ..Default::default()
+            },
+            game_data: self.game_data,
+        }
+    }
+}
+
+/// Applies combat events to an [`Encounter`].
+pub struct EncounterState {
+    pub encounter: Encounter,
+    game_data: Arc<dyn GameDataProvider + Send + Sync>,
+}
+
+impl Default for EncounterState {
+    fn default() -> Self {
+        EncounterBuilder::default().build()
+    }
+}
+
+impl EncounterState {
+    pub fn builder() -> EncounterBuilder {
+        EncounterBuilder::new()
+    }
+
+    /// Clears all combat data while keeping the local player and settings.
+    pub fn reset(&mut self) {
+        self.encounter = Encounter {
+            local_player: std::mem::take(&mut self.encounter.local_player),
+            difficulty: self.encounter.difficulty.take(),
+            boss_only_damage: self.encounter.boss_only_damage,
+            ..Default::default()
+        };
+    }
+
+    pub fn game_data(&self) -> &Arc<dyn GameDataProvider + Send + Sync> {
+        &self.game_data
+    }
+
+    /// Registers an entity, or refreshes its identity if it is already tracked.
+    pub fn on_new_entity(&mut self, entity: &Entity) {
+        if entity.is_local_player {
+            self.encounter.local_player.clone_from(&entity.name);
+        }
+
+        self.encounter
+            .entities
+            .entry(entity.name.clone())
+            .and_modify(|existing| existing.update(entity))
+            .or_insert_with(|| EncounterEntity::from(entity));
+    }
+
+    /// Records a new cast of `skill_id` by `source`.
+    pub fn on_skill_start(&mut self, source: &Entity, skill_id: u32, timestamp: i64) {
+        let relative_timestamp = if self.encounter.fight_start == 0 {
+            0
+        } else {
+            timestamp - self.encounter.fight_start
+        };
+
+        let game_data = &self.game_data;
+        let entity = self
+            .encounter
+            .entities
+            .entry(source.name.clone())
+            .or_insert_with(|| EncounterEntity::from(source));
+        let skill = entity
+            .skills
+            .entry(skill_id)
+            .or_insert_with(|| new_skill(game_data.as_ref(), skill_id, None));
+
+        entity.skill_stats.casts += 1;
+        skill.casts += 1;
+        skill.last_cast_damage = 0;
+        skill.cast_log.push(relative_timestamp as i32);
+        skill.skill_cast_log.push(SkillCast {
+            timestamp: relative_timestamp,
+            last: relative_timestamp,
+            hits: Vec::new(),
+        });
+    }
+
+    pub fn on_damage(&mut self, event: &DamageEvent) -> DamageResult {
+        let mut result = DamageResult::default();
+
+        if !event.is_valid || event.hit_flag == HitFlag::Invincible {
+            return result;
+        }
+
+        if event.hit_flag == HitFlag::DamageShare
+            && event.skill_id == 0
+            && event.skill_effect_id.is_none()
+        {
+            return result;
+        }
+
+        let owner = event.owner_entity;
+        let target = &event.target_entity;
+
+        if self.encounter.boss_only_damage
+            && owner.entity_type == EntityType::Player
+            && target.entity_type != EntityType::Boss
+        {
+            return result;
+        }
+
+        // skills without an id (battle items, some dots) are keyed by their effect
+        let skill_id = if event.skill_id == 0 || event.is_battle_item {
+            event.skill_effect_id.unwrap_or(event.skill_id)
+        } else {
+            event.skill_id
+        };
+
+        // overkill on non-players only counts up to the remaining hp
+        let mut damage = event.damage;
+        if target.entity_type != EntityType::Player && event.target_current_hp < 0 {
+            damage = (damage + event.target_current_hp).max(0);
+        }
+
+        if self.encounter.fight_start == 0 {
+            self.encounter.fight_start = event.timestamp;
+            result.is_raid_start = true;
+        }
+        self.encounter.last_combat_packet = event.timestamp;
+        let relative_timestamp = event.timestamp - self.encounter.fight_start;
+
+        self.apply_damage_taken(target, event, damage);
+
+        let is_player = owner.entity_type == EntityType::Player;
+        let attribution = if is_player {
+            self.register_status_effects(event)
+        } else {
+            BuffAttribution::default()
+        };
+
+        let is_hyper_awakening = self
+            .game_data
+            .skill(skill_id)
+            .is_some_and(|skill| skill.skill_type == "hyperawakening");
+
+        let is_crit = matches!(
+            event.hit_flag,
+            HitFlag::Critical | HitFlag::DamageOverTimeCritical
+        );
+        let is_back_attack = event.hit_option == HitOption::BackAttack;
+        let is_front_attack = event.hit_option == HitOption::FrontalAttack;
+
+        let game_data = &self.game_data;
+        let source = self
+            .encounter
+            .entities
+            .entry(owner.name.clone())
+            .or_insert_with(|| EncounterEntity::from(owner));
+        let skill = source.skills.entry(skill_id).or_insert_with(|| {
+            let effect_id = event.is_battle_item.then_some(skill_id);
+            new_skill(game_data.as_ref(), skill_id, effect_id)
+        });
+
+        let mut skill_hit = SkillHit {
+            timestamp: relative_timestamp,
+            damage,
+            crit: is_crit,
+            back_attack: is_back_attack,
+            front_attack: is_front_attack,
+            ..Default::default()
+        };
+
+        source.damage_stats.damage_dealt += damage;
+        source.skill_stats.hits += 1;
+        skill.total_damage += damage;
+        skill.hits += 1;
+        skill.max_damage = skill.max_damage.max(damage);
+        skill.last_timestamp = relative_timestamp;
+
+        if is_hyper_awakening {
+            source.damage_stats.hyper_awakening_damage += damage;
+        }
+
+        if is_crit {
+            source.skill_stats.crits += 1;
+            source.damage_stats.crit_damage += damage;
+            skill.crits += 1;
+            skill.crit_damage += damage;
+        }
+
+        if is_back_attack {
+            source.skill_stats.back_attacks += 1;
+            source.damage_stats.back_attack_damage += damage;
+            skill.back_attacks += 1;
+            skill.back_attack_damage += damage;
+        }
+
+        if is_front_attack {
+            source.skill_stats.front_attacks += 1;
+            source.damage_stats.front_attack_damage += damage;
+            skill.front_attacks += 1;
+            skill.front_attack_damage += damage;
+        }
+
+        if is_player {
+            if !is_hyper_awakening {
+                if attribution.buffed_by_support {
+                    skill.buffed_by_support += damage;
+                    source.damage_stats.buffed_by_support += damage;
+                }
+                if attribution.buffed_by_identity {
+                    skill.buffed_by_identity += damage;
+                    source.damage_stats.buffed_by_identity += damage;
+                }
+                if attribution.buffed_by_hat {
+                    skill.buffed_by_hat += damage;
+                    source.damage_stats.buffed_by_hat += damage;
+                }
+                if attribution.debuffed_by_support {
+                    skill.debuffed_by_support += damage;
+                    source.damage_stats.debuffed_by_support += damage;
+                }
+            }
+
+            for &buff_id in &event.se_on_source_ids {
+                *skill.buffed_by.entry(buff_id).or_default() += damage;
+                *source.damage_stats.buffed_by.entry(buff_id).or_default() += damage;
+                skill_hit.buffed_by.push(buff_id);
+            }
+
+            for &debuff_id in &event.se_on_target_ids {
+                *skill.debuffed_by.entry(debuff_id).or_default() += damage;
+                *source.damage_stats.debuffed_by.entry(debuff_id).or_default() += damage;
+                skill_hit.debuffed_by.push(debuff_id);
+            }
+        }
+
+        match skill.skill_cast_log.last_mut() {
+            Some(cast) => {
+                cast.last = relative_timestamp;
+                cast.hits.push(skill_hit);
+            }
+            None => {
+                // damage without a recorded cast (dots, summons) opens one implicitly
+                source.skill_stats.casts += 1;
+                skill.casts += 1;
+                skill.last_cast_damage = 0;
+                skill.cast_log.push(relative_timestamp as i32);
+                skill.skill_cast_log.push(SkillCast {
+                    timestamp: relative_timestamp,
+                    last: relative_timestamp,
+                    hits: vec![skill_hit],
+                });
+            }
+        }
+
+        skill.last_cast_damage += damage;
+        skill.max_damage_cast = skill.max_damage_cast.max(skill.last_cast_damage);
+
+        if is_player {
+            let stats = &mut self.encounter.encounter_damage_stats;
+            stats.total_damage_dealt += damage;
+            stats.top_damage_dealt = stats.top_damage_dealt.max(source.damage_stats.damage_dealt);
+        }
+
+        result
+    }
+
+    /// Recomputes duration and dps of the encounter, its entities and their skills.
+    pub fn update_dps(&mut self) {
+        let encounter = &mut self.encounter;
+        encounter.duration = (encounter.last_combat_packet - encounter.fight_start).max(0);
+
+        let duration_seconds = encounter.duration / 1000;
+        if duration_seconds == 0 {
+            return;
+        }
+
+        for entity in encounter.entities.values_mut() {
+            entity.damage_stats.dps = entity.damage_stats.damage_dealt / duration_seconds;
+            for skill in entity.skills.values_mut() {
+                skill.dps = skill.total_damage / duration_seconds;
+            }
+        }
+
+        let stats = &mut encounter.encounter_damage_stats;
+        stats.dps = stats.total_damage_dealt / duration_seconds;
+    }
+
+    fn apply_damage_taken(&mut self, target: &Entity, event: &DamageEvent, damage: i64) {
+        let entity = self
+            .encounter
+            .entities
+            .entry(target.name.clone())
+            .or_insert_with(|| EncounterEntity::from(target));
+
+        entity.current_hp = event.target_current_hp;
+        entity.max_hp = event.target_max_hp;
+        entity.damage_stats.damage_taken += damage;
+
+        if target.entity_type == EntityType::Boss {
+            self.encounter.current_boss_name.clone_from(&target.name);
+        }
+
+        if target.entity_type == EntityType::Player {
+            let damage_taken = entity.damage_stats.damage_taken;
+            let stats = &mut self.encounter.encounter_damage_stats;
+            stats.total_damage_taken += damage;
+
+            if damage_taken > stats.top_damage_taken {
+                stats.top_damage_taken = damage_taken;
+                stats.most_damage_taken_entity = MostDamageTakenEntity {
+                    name: target.name.clone(),
+                    damage_taken,
+                };
+            }
+        }
+    }
+
+    /// Resolves unseen buff and debuff ids and works out which support categories the hit benefited from.
+    fn register_status_effects(&mut self, event: &DamageEvent) -> BuffAttribution {
+        let mut attribution = BuffAttribution::default();
+        let stats = &mut self.encounter.encounter_damage_stats;
+
+        for &buff_id in &event.se_on_source_ids {
+            if !stats.buffs.contains_key(&buff_id) && !stats.unknown_buffs.contains(&buff_id) {
+                match self.game_data.status_effect(buff_id) {
+                    Some(status_effect) => {
+                        stats.buffs.insert(buff_id, status_effect);
+                    }
+                    None => {
+                        stats.unknown_buffs.insert(buff_id);
+                    }
+                }
+            }
+
+            if is_hat_buff(buff_id) {
+                attribution.buffed_by_hat = true;
+                continue;
+            }
+
+            if let Some(buff) = stats.buffs.get(&buff_id) {
+                if is_party_damage_buff_from_support(buff) {
+                    match buff.buff_category.as_str() {
+                        "classskill" | "arkpassive" => attribution.buffed_by_support = true,
+                        "identity" => attribution.buffed_by_identity = true,
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        for &debuff_id in &event.se_on_target_ids {
+            if !stats.debuffs.contains_key(&debuff_id) && !stats.unknown_buffs.contains(&debuff_id) {
+                match self.game_data.status_effect(debuff_id) {
+                    Some(status_effect) => {
+                        stats.debuffs.insert(debuff_id, status_effect);
+                    }
+                    None => {
+                        stats.unknown_buffs.insert(debuff_id);
+                    }
+                }
+            }
+
+            if let Some(debuff) = stats.debuffs.get(&debuff_id) {
+                if is_party_damage_buff_from_support(debuff)
+                    && matches!(debuff.buff_category.as_str(), "classskill" | "arkpassive")
+                {
+                    attribution.debuffed_by_support = true;
+                }
+            }
+        }
+
+        attribution
+    }
+}
+
+fn is_party_damage_buff_from_support(status_effect: &StatusEffect) -> bool {
+    let is_support_skill = status_effect
+        .source
+        .skill
+        .as_ref()
+        .and_then(|skill| Class::try_from(skill.class_id).ok())
+        .is_some_and(|class| class.is_support());
+
+    is_support_skill
+        && status_effect.buff_type & StatusEffectBuffTypeFlags::DMG.bits() != 0
+        && status_effect.target == StatusEffectTarget::PARTY
+}
+
+fn new_skill(game_data: &dyn GameDataProvider, skill_id: u32, skill_effect_id: Option<u32>) -> Skill {
+    let mut skill = Skill {
+        id: skill_id,
+        name: skill_id.to_string(),
+        ..Default::default()
+    };
+
+    if let Some(effect) = skill_effect_id.and_then(|id| game_data.skill_effect(id)) {
+        if let Some(name) = &effect.item_name {
+            skill.name.clone_from(name);
+        }
+        skill.icon = effect.icon.clone().unwrap_or_default();
+    } else if let Some(data) = game_data.skill(skill_id) {
+        if let Some(name) = &data.name {
+            skill.name.clone_from(name);
+        }
+        skill.icon = data.icon.clone().unwrap_or_default();
+        skill.summon_sources.clone_from(&data.summon_source_skills);
+    }
+
+    skill
+}
+
+#[cfg(test)]
+mod tests {
+    use hashbrown::HashMap;
+
+    use super::*;
+
+    struct TestGameData {
+        status_effects: HashMap<u32, StatusEffect>,
+    }
+
+    impl GameDataProvider for TestGameData {
+        fn status_effect(&self, status_effect_id: u32) -> Option<StatusEffect> {
+            self.status_effects.get(&status_effect_id).cloned()
+        }
+    }
+
+    fn player(id: u64, name: &str) -> Entity {
+        Entity {
+            id,
+            name: name.to_string(),
+            entity_type: EntityType::Player,
+            class_id: Class::Berserker,
+            character_id: id,
+            ..Default::default()
+        }
+    }
+
+    fn boss() -> Entity {
+        Entity {
+            id: 1000,
+            name: "Thaemine".to_string(),
+            entity_type: EntityType::Boss,
+            ..Default::default()
+        }
+    }
+
+    fn damage_event<'a>(source: &'a Entity, target: Entity, damage: i64, timestamp: i64) -> DamageEvent<'a> {
+        DamageEvent {
+            is_valid: true,
+            is_battle_item: false,
+            hit_flag: HitFlag::Normal,
+            hit_option: HitOption::None,
+            skill_id: 16140,
+            skill_effect_id: None,
+            damage,
+            target_current_hp: 1_000_000,
+            target_max_hp: 2_000_000,
+            owner_entity: source,
+            source_entity: source,
+            target_entity: target,
+            timestamp,
+            se_on_source: vec![],
+            se_on_source_ids: vec![],
+            se_on_target: vec![],
+            se_on_target_ids: vec![],
+        }
+    }
+
+    #[test]
+    fn should_detect_raid_start_on_first_damage() {
+        let source = player(1, "Alice");
+        let mut state = EncounterState::default();
+
+        let first = state.on_damage(&damage_event(&source, boss(), 100, 1000));
+        let second = state.on_damage(&damage_event(&source, boss(), 100, 2000));
+
+        assert!(first.is_raid_start);
+        assert!(!second.is_raid_start);
+        assert_eq!(state.encounter.fight_start, 1000);
+        assert_eq!(state.encounter.last_combat_packet, 2000);
+        assert_eq!(state.encounter.current_boss_name, "Thaemine");
+    }
+
+    #[test]
+    fn should_count_crits_and_positionals() {
+        let source = player(1, "Alice");
+        let mut state = EncounterState::default();
+
+        let mut event = damage_event(&source, boss(), 100, 1000);
+        event.hit_flag = HitFlag::Critical;
+        event.hit_option = HitOption::BackAttack;
+        state.on_damage(&event);
+
+        let mut event = damage_event(&source, boss(), 50, 1500);
+        event.hit_option = HitOption::FrontalAttack;
+        state.on_damage(&event);
+
+        let entity = &state.encounter.entities["Alice"];
+        let skill = &entity.skills[&16140];
+        assert_eq!(entity.damage_stats.damage_dealt, 150);
+        assert_eq!(entity.damage_stats.crit_damage, 100);
+        assert_eq!(entity.damage_stats.back_attack_damage, 100);
+        assert_eq!(entity.damage_stats.front_attack_damage, 50);
+        assert_eq!(entity.skill_stats.hits, 2);
+        assert_eq!(skill.crits, 1);
+        assert_eq!(skill.casts, 1);
+        assert_eq!(skill.max_damage, 100);
+        assert_eq!(skill.max_damage_cast, 150);
+        assert_eq!(skill.skill_cast_log[0].hits.len(), 2);
+        assert_eq!(skill.skill_cast_log[0].hits[1].timestamp, 500);
+        assert_eq!(skill.last_timestamp, 500);
+        assert_eq!(state.encounter.encounter_damage_stats.total_damage_dealt, 150);
+        assert_eq!(state.encounter.entities["Thaemine"].damage_stats.damage_taken, 150);
+    }
+
+    #[test]
+    fn should_track_max_damage_per_cast() {
+        let source = player(1, "Alice");
+        let mut state = EncounterState::default();
+
+        state.on_damage(&damage_event(&source, boss(), 100, 1000));
+        state.on_damage(&damage_event(&source, boss(), 50, 1200));
+        state.on_skill_start(&source, 16140, 2000);
+        state.on_damage(&damage_event(&source, boss(), 120, 2100));
+        assert_eq!(state.encounter.entities["Alice"].skills[&16140].max_damage_cast, 150);
+
+        state.on_damage(&damage_event(&source, boss(), 40, 2200));
+        let skill = &state.encounter.entities["Alice"].skills[&16140];
+        assert_eq!(skill.max_damage_cast, 160);
+        assert_eq!(skill.skill_cast_log.len(), 2);
+    }
+
+    #[test]
+    fn should_ignore_invincible_hits() {
+        let source = player(1, "Alice");
+        let mut state = EncounterState::default();
+
+        let mut event = damage_event(&source, boss(), 100, 1000);
+        event.hit_flag = HitFlag::Invincible;
+        let result = state.on_damage(&event);
+
+        assert!(!result.is_raid_start);
+        assert!(state.encounter.entities.is_empty());
+    }
+
+    #[test]
+    fn should_attribute_support_identity_and_hat_buffs() {
+        let support_skill = SkillData {
+            class_id: Class::Bard as u32,
+            ..Default::default()
+        };
+        let party_buff = |buff_category: &str| StatusEffect {
+            target: StatusEffectTarget::PARTY,
+            buff_category: buff_category.to_string(),
+            buff_type: StatusEffectBuffTypeFlags::DMG.bits(),
+            source: StatusEffectSource {
+                skill: Some(support_skill.clone()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let game_data = TestGameData {
+            status_effects: HashMap::from([
+                (211400, party_buff("classskill")),
+                (211606, party_buff("identity")),
+                (210230, party_buff("classskill")),
+            ]),
+        };
+        let mut state = EncounterState::builder()
+            .game_data(Arc::new(game_data))
+            .build();
+
+        let source = player(1, "Alice");
+        let mut event = damage_event(&source, boss(), 100, 1000);
+        event.se_on_source_ids = vec![211400, 211606, 212305, 999];
+        event.se_on_target_ids = vec![210230];
+        state.on_damage(&event);
+
+        let stats = &state.encounter.entities["Alice"].damage_stats;
+        assert_eq!(stats.buffed_by_support, 100);
+        assert_eq!(stats.buffed_by_identity, 100);
+        assert_eq!(stats.buffed_by_hat, 100);
+        assert_eq!(stats.debuffed_by_support, 100);
+        assert_eq!(stats.buffed_by[&999], 100);
+        assert!(state.encounter.encounter_damage_stats.unknown_buffs.contains(&999));
+        assert!(state.encounter.encounter_damage_stats.debuffs.contains_key(&210230));
+    }
+
+    #[test]
+    fn should_skip_non_boss_damage_when_boss_only() {
+        let source = player(1, "Alice");
+        let mut state = EncounterState::builder().boss_only_damage(true).build();
+        let add = Entity {
+            name: "Add".to_string(),
+            entity_type: EntityType::Monster,
+            ..Default::default()
+        };
+
+        state.on_damage(&damage_event(&source, add, 100, 1000));
+        state.on_damage(&damage_event(&source, boss(), 100, 2000));
+
+        assert_eq!(state.encounter.fight_start, 2000);
+        assert_eq!(state.encounter.entities["Alice"].damage_stats.damage_dealt, 100);
+    }
+
+    #[test]
+    fn should_compute_dps() {
+        let source = player(1, "Alice");
+        let mut state = EncounterState::default();
+
+        state.on_damage(&damage_event(&source, boss(), 1000, 1000));
+        state.on_damage(&damage_event(&source, boss(), 1000, 11000));
+        state.update_dps();
+
+        assert_eq!(state.encounter.duration, 10000);
+        assert_eq!(state.encounter.entities["Alice"].damage_stats.dps, 200);
+        assert_eq!(state.encounter.encounter_damage_stats.dps, 200);
+    }
+}
@@ -0,0 +1,271 @@
+use hashbrown::HashMap;
+
+use crate::models::{Entity, EntityType};
+
+/// Entities currently spawned in the zone, indexed by entity id and by character id.
+#[derive(Debug, Default, Clone)]
+pub struct EntityTracker {
+    entities: HashMap<u64, Entity>,
+    character_ids: HashMap<u64, u64>,
+    local_player_id: Option<u64>,
+}
+
+impl EntityTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an entity and returns the one previously spawned with the same id.
+    ///
+    /// The game reuses ids of despawned entities, so a spawn always replaces the old entry.
+    /// A player respawning under a new id (e.g. after a loading screen) drops its stale entry,
+    /// and stays the local player even if the respawn does not flag it.
+    pub fn spawn(&mut self, mut entity: Entity) -> Option<Entity> {
+        if entity.character_id != 0 {
+            let stale_id = self
+                .character_ids
+                .get(&entity.character_id)
+                .copied()
+                .filter(|&stale_id| stale_id != entity.id);
+
+            if let Some(stale) = stale_id.and_then(|stale_id| self.entities.remove(&stale_id)) {
+                entity.is_local_player |= self.local_player_id == Some(stale.id);
+                self.unlink(&stale);
+            }
+
+            self.character_ids.insert(entity.character_id, entity.id);
+        }
+
+        if entity.is_local_player {
+            self.local_player_id = Some(entity.id);
+        }
+
+        let replaced = self.entities.insert(entity.id, entity)?;
+        self.unlink(&replaced);
+        Some(replaced)
+    }
+
+    pub fn despawn(&mut self, id: u64) -> Option<Entity> {
+        let entity = self.entities.remove(&id)?;
+        self.unlink(&entity);
+        Some(entity)
+    }
+
+    /// Drops the indices pointing at a removed entity, unless they were taken over by a newer spawn.
+    fn unlink(&mut self, removed: &Entity) {
+        if removed.character_id != 0 && self.character_ids.get(&removed.character_id) == Some(&removed.id) {
+            let still_spawned = self
+                .entities
+                .get(&removed.id)
+                .is_some_and(|entity| entity.character_id == removed.character_id);
+            if !still_spawned {
+                self.character_ids.remove(&removed.character_id);
+            }
+        }
+
+        let still_local = self
+            .entities
+            .get(&removed.id)
+            .is_some_and(|entity| entity.is_local_player);
+        if self.local_player_id == Some(removed.id) && !still_local {
+            self.local_player_id = None;
+        }
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Entity> {
+        self.entities.get(&id)
+    }
+
+    pub fn by_character_id(&self, character_id: u64) -> Option<&Entity> {
+        self.entities.get(self.character_ids.get(&character_id)?)
+    }
+
+    pub fn local_player(&self) -> Option<&Entity> {
+        self.entities.get(&self.local_player_id?)
+    }
+
+    /// Renames the local player, e.g. once its real name is known. Returns the previous name.
+    pub fn rename_local_player(&mut self, name: impl Into<String>) -> Option<String> {
+        let entity = self.entities.get_mut(&self.local_player_id?)?;
+        Some(std::mem::replace(&mut entity.name, name.into()))
+    }
+
+    /// Follows `owner_id` from summons and projectiles to the entity credited with their damage.
+    ///
+    /// Chains of any depth are resolved. When an owner in the chain is not spawned (anymore),
+    /// the last known entity is returned; cycles stop once every entity was visited.
+    pub fn owner(&self, id: u64) -> Option<&Entity> {
+        let mut entity = self.entities.get(&id)?;
+
+        for _ in 0..self.entities.len() {
+            if !matches!(entity.entity_type, EntityType::Summon | EntityType::Projectile) {
+                break;
+            }
+
+            match self.entities.get(&entity.owner_id) {
+                Some(owner) => entity = owner,
+                None => break,
+            }
+        }
+
+        Some(entity)
+    }
+
+    /// The player responsible for damage dealt by `id`, if any.
+    pub fn owning_player(&self, id: u64) -> Option<&Entity> {
+        self.owner(id).filter(|entity| entity.entity_type == EntityType::Player)
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = &Entity> {
+        self.entities.values()
+    }
+
+    /// Forgets every entity, e.g. on a zone change where all ids are reassigned.
+    pub fn clear(&mut self) {
+        self.entities.clear();
+        self.character_ids.clear();
+        self.local_player_id = None;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(id: u64, character_id: u64, name: &str) -> Entity {
+        Entity {
+            id,
+            character_id,
+            name: name.to_string(),
+            entity_type: EntityType::Player,
+            ..Default::default()
+        }
+    }
+
+    fn owned(id: u64, entity_type: EntityType, owner_id: u64) -> Entity {
+        Entity {
+            id,
+            entity_type,
+            owner_id,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn should_resolve_owner_chains() {
+        let mut tracker = EntityTracker::new();
+        tracker.spawn(player(1, 100, "Alice"));
+        tracker.spawn(owned(2, EntityType::Summon, 1));
+        tracker.spawn(owned(3, EntityType::Projectile, 2));
+        tracker.spawn(owned(4, EntityType::Projectile, 3));
+
+        assert_eq!(tracker.owning_player(4).unwrap().name, "Alice");
+        assert_eq!(tracker.owning_player(1).unwrap().name, "Alice");
+        assert_eq!(tracker.owner(3).unwrap().id, 1);
+    }
+
+    #[test]
+    fn should_stop_at_missing_owners_and_cycles() {
+        let mut tracker = EntityTracker::new();
+        tracker.spawn(owned(2, EntityType::Summon, 9));
+        tracker.spawn(owned(3, EntityType::Projectile, 4));
+        tracker.spawn(owned(4, EntityType::Projectile, 3));
+
+        assert_eq!(tracker.owner(2).unwrap().id, 2);
+        assert!(tracker.owning_player(2).is_none());
+        assert!(tracker.owner(3).is_some());
+        assert!(tracker.owner(5).is_none());
+    }
+
+    #[test]
+    fn should_handle_despawn_and_id_reuse() {
+        let mut tracker = EntityTracker::new();
+        tracker.spawn(player(1, 100, "Alice"));
+        tracker.spawn(owned(2, EntityType::Summon, 1));
+
+        let despawned = tracker.despawn(1).unwrap();
+        assert_eq!(despawned.name, "Alice");
+        assert!(tracker.by_character_id(100).is_none());
+        assert_eq!(tracker.owner(2).unwrap().id, 2);
+
+        tracker.spawn(player(1, 200, "Bob"));
+        assert_eq!(tracker.owning_player(2).unwrap().name, "Bob");
+
+        let replaced = tracker.spawn(player(1, 300, "Charlie")).unwrap();
+        assert_eq!(replaced.name, "Bob");
+        assert!(tracker.by_character_id(200).is_none());
+        assert_eq!(tracker.by_character_id(300).unwrap().name, "Charlie");
+    }
+
+    #[test]
+    fn should_drop_stale_entry_when_player_respawns_with_new_id() {
+        let mut tracker = EntityTracker::new();
+        tracker.spawn(player(1, 100, "Alice"));
+
+        tracker.spawn(player(7, 100, "Alice"));
+
+        assert!(tracker.get(1).is_none());
+        assert_eq!(tracker.by_character_id(100).unwrap().id, 7);
+        assert_eq!(tracker.len(), 1);
+    }
+
+    #[test]
+    fn should_track_and_rename_local_player() {
+        let mut tracker = EntityTracker::new();
+        tracker.spawn(Entity {
+            is_local_player: true,
+            ..player(1, 100, "You")
+        });
+
+        assert_eq!(tracker.rename_local_player("Alice").as_deref(), Some("You"));
+        assert_eq!(tracker.local_player().unwrap().name, "Alice");
+        assert_eq!(tracker.by_character_id(100).unwrap().name, "Alice");
+
+        tracker.despawn(1);
+        assert!(tracker.local_player().is_none());
+        assert!(tracker.rename_local_player("Bob").is_none());
+    }
+
+    #[test]
+    fn should_drop_local_player_when_id_is_reused() {
+        let mut tracker = EntityTracker::new();
+        tracker.spawn(Entity {
+            is_local_player: true,
+            ..player(1, 100, "Alice")
+        });
+
+        tracker.spawn(Entity {
+            id: 1,
+            entity_type: EntityType::Monster,
+            name: "Monster".to_string(),
+            ..Default::default()
+        });
+
+        assert!(tracker.local_player().is_none());
+        assert!(tracker.rename_local_player("Bob").is_none());
+        assert_eq!(tracker.get(1).unwrap().name, "Monster");
+    }
+
+    #[test]
+    fn should_keep_local_player_across_respawn_without_flag() {
+        let mut tracker = EntityTracker::new();
+        tracker.spawn(Entity {
+            is_local_player: true,
+            ..player(1, 100, "Alice")
+        });
+
+        tracker.spawn(player(7, 100, "Alice"));
+
+        assert_eq!(tracker.local_player().unwrap().id, 7);
+        assert_eq!(tracker.rename_local_player("Alicia").as_deref(), Some("Alice"));
+        assert!(tracker.get(1).is_none());
+    }
+}
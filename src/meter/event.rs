@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::*;
+
+use super::PartyMember;
+
+/// Input vocabulary of the meter: everything the packet parser reports, without borrowed data.
+///
+/// Timestamps are milliseconds since the unix epoch. Entities are referenced by their id and
+/// resolved by the [`MeterDriver`](super::MeterDriver).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum MeterEvent {
+    NewPlayer(Entity),
+    NewNpc(Entity),
+    /// Summons and projectiles, whose damage is credited to the entity in `owner_id`.
+    NewSummon(Entity),
+    /// The id may be reused by a later spawn.
+    Despawn {
+        entity_id: u64,
+        timestamp: i64,
+    },
+    SkillStart {
+        source_id: u64,
+        skill_id: u32,
+        timestamp: i64,
+    },
+    Damage(DamageRecord),
+    StatusEffectAdd(StatusEffectDetails),
+    StatusEffectRemove {
+        target_id: u64,
+        target_type: StatusEffectTargetType,
+        instance_id: u32,
+        timestamp: i64,
+    },
+    StatusEffectUpdate {
+        target_id: u64,
+        target_type: StatusEffectTargetType,
+        instance_id: u32,
+        change: StatusEffectChange,
+        timestamp: i64,
+    },
+    Stagger {
+        stagger: Stagger,
+        timestamp: i64,
+    },
+    /// Identity gauges of the local player.
+    Identity {
+        identity: Identity,
+        timestamp: i64,
+    },
+    Death {
+        target_id: u64,
+        timestamp: i64,
+    },
+    /// Entity ids are reassigned after a zone change.
+    ZoneChange {
+        timestamp: i64,
+    },
+    RaidResult {
+        cleared: bool,
+        timestamp: i64,
+    },
+    /// Full member list of a party, sent on changes and when members connect or disconnect.
+    PartyInfo {
+        party_instance_id: u32,
+        raid_instance_id: u32,
+        members: Vec<PartyMember>,
+        timestamp: i64,
+    },
+    PartyLeave {
+        party_instance_id: u32,
+        character_id: u64,
+        timestamp: i64,
+    },
+}
+
+impl MeterEvent {
+    pub fn timestamp(&self) -> i64 {
+        match self {
+            MeterEvent::NewPlayer(_) | MeterEvent::NewNpc(_) | MeterEvent::NewSummon(_) => 0,
+            MeterEvent::StatusEffectAdd(effect) => effect.timestamp.timestamp_millis(),
+            MeterEvent::Damage(damage) => damage.timestamp,
+            MeterEvent::Despawn { timestamp, .. }
+            | MeterEvent::SkillStart { timestamp, .. }
+            | MeterEvent::StatusEffectRemove { timestamp, .. }
+            | MeterEvent::StatusEffectUpdate { timestamp, .. }
+            | MeterEvent::Stagger { timestamp, .. }
+            | MeterEvent::Identity { timestamp, .. }
+            | MeterEvent::Death { timestamp, .. }
+            | MeterEvent::ZoneChange { timestamp }
+            | MeterEvent::RaidResult { timestamp, .. }
+            | MeterEvent::PartyInfo { timestamp, .. }
+            | MeterEvent::PartyLeave { timestamp, .. } => *timestamp,
+        }
+    }
+}
+
+/// Owned form of a [`DamageEvent`]. Status effects on source and target are filled in by the driver.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DamageRecord {
+    pub source_id: u64,
+    pub target_id: u64,
+    pub skill_id: u32,
+    pub skill_effect_id: Option<u32>,
+    pub damage: i64,
+    pub hit_flag: HitFlag,
+    pub hit_option: HitOption,
+    pub target_current_hp: i64,
+    pub target_max_hp: i64,
+    pub is_battle_item: bool,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum StatusEffectChange {
+    /// Remaining value, e.g. of a shield.
+    Value(u64),
+    StackCount(u8),
+    Refresh {
+        expiration_delay: f32,
+        end_tick: u64,
+    },
+}
@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use crate::models::{Npc, SkillData, SkillEffectData, StatusEffect};
+
+/// Static game data needed while aggregating an encounter.
+///
+/// Every lookup defaults to `None`, so `()` can be used when no data is loaded;
+/// skills then fall back to their id as name and buffs are tracked as unknown.
+pub trait GameDataProvider {
+    fn skill(&self, _skill_id: u32) -> Option<&SkillData> {
+        None
+    }
+
+    fn skill_effect(&self, _skill_effect_id: u32) -> Option<&SkillEffectData> {
+        None
+    }
+
+    fn status_effect(&self, _status_effect_id: u32) -> Option<StatusEffect> {
+        None
+    }
+
+    fn npc(&self, _npc_id: u32) -> Option<&Npc> {
+        None
+    }
+}
+
+impl GameDataProvider for () {}
+
+impl<T: GameDataProvider + ?Sized> GameDataProvider for Arc<T> {
+    fn skill(&self, skill_id: u32) -> Option<&SkillData> {
+        (**self).skill(skill_id)
+    }
+
+    fn skill_effect(&self, skill_effect_id: u32) -> Option<&SkillEffectData> {
+        (**self).skill_effect(skill_effect_id)
+    }
+
+    fn status_effect(&self, status_effect_id: u32) -> Option<StatusEffect> {
+        (**self).status_effect(status_effect_id)
+    }
+
+    fn npc(&self, npc_id: u32) -> Option<&Npc> {
+        (**self).npc(npc_id)
+    }
+}
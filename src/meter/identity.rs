@@ -0,0 +1,189 @@
+use hashbrown::HashMap;
+
+use crate::models::*;
+
+/// Collects identity gauge updates of one player and summarises them per class.
+#[derive(Debug, Clone)]
+pub struct IdentityTracker {
+    class: Class,
+    max_gauge: u32,
+    log: IdentityLog,
+}
+
+impl IdentityTracker {
+    /// `max_gauge` is the value of a full `gauge1` for the player's class.
+    pub fn new(class: Class, max_gauge: u32) -> Self {
+        Self {
+            class,
+            max_gauge,
+            log: IdentityLog::new(),
+        }
+    }
+
+    /// Records the gauges received `relative_timestamp` milliseconds after the fight started.
+    pub fn on_identity(&mut self, identity: &Identity, relative_timestamp: i64) {
+        self.log.push((
+            relative_timestamp,
+            (identity.gauge1, identity.gauge2, identity.gauge3),
+        ));
+    }
+
+    pub fn log(&self) -> &IdentityLog {
+        &self.log
+    }
+
+    fn percent(&self, gauge: u32) -> f32 {
+        if self.max_gauge == 0 {
+            return 0.0;
+        }
+
+        (gauge.min(self.max_gauge) as f64 * 100.0 / self.max_gauge as f64) as f32
+    }
+
+    /// Builds the class specific stats for a fight lasting `duration` milliseconds.
+    pub fn stats(&self, duration: i64) -> Option<IdentityStats> {
+        if self.log.is_empty() {
+            return None;
+        }
+
+        let seconds = |timestamp: i64| (timestamp / 1000) as i32;
+        let average = self.average(duration);
+
+        let stats = match self.class {
+            Class::Arcanist => {
+                let mut card_draws: HashMap<u32, u32> = HashMap::new();
+                let mut previous = (0, 0);
+
+                for &(_, (_, card1, card2)) in &self.log {
+                    // a card slot changing to a non-empty card is a draw
+                    if card1 != 0 && card1 != previous.0 {
+                        *card_draws.entry(card1).or_default() += 1;
+                    }
+                    if card2 != 0 && card2 != previous.1 {
+                        *card_draws.entry(card2).or_default() += 1;
+                    }
+                    previous = (card1, card2);
+                }
+
+                IdentityStats::Arcanist(IdentityArcanist {
+                    log: self
+                        .log
+                        .iter()
+                        .map(|&(timestamp, (gauge, card1, card2))| {
+                            (seconds(timestamp), (self.percent(gauge), card1, card2))
+                        })
+                        .collect(),
+                    average,
+                    card_draws,
+                })
+            }
+            Class::Bard | Class::Artist => IdentityStats::ArtistBard(IdentityArtistBard {
+                log: self
+                    .log
+                    .iter()
+                    .map(|&(timestamp, (gauge, bubbles, _))| (seconds(timestamp), (self.percent(gauge), bubbles)))
+                    .collect(),
+                average,
+            }),
+            _ => IdentityStats::Generic(IdentityGeneric {
+                log: self
+                    .log
+                    .iter()
+                    .map(|&(timestamp, (gauge, _, _))| (seconds(timestamp), self.percent(gauge)))
+                    .collect(),
+                average,
+            }),
+        };
+
+        Some(stats)
+    }
+
+    /// Time-weighted average gauge percentage from the first update to the end of the fight.
+    fn average(&self, duration: i64) -> f64 {
+        let Some(&(start, _)) = self.log.first() else {
+            return 0.0;
+        };
+
+        let end = duration.max(self.log.last().map_or(start, |(timestamp, _)| *timestamp));
+        if end <= start {
+            return self.log.last().map_or(0.0, |&(_, (gauge, _, _))| self.percent(gauge) as f64);
+        }
+
+        let weighted: f64 = self
+            .log
+            .iter()
+            .enumerate()
+            .map(|(index, &(timestamp, (gauge, _, _)))| {
+                let until = self.log.get(index + 1).map_or(end, |(next, _)| *next);
+                self.percent(gauge) as f64 * (until - timestamp) as f64
+            })
+            .sum();
+
+        weighted / (end - start) as f64
+    }
+
+    pub fn write_to(&self, skill_stats: &mut SkillStats, duration: i64) {
+        skill_stats.identity_stats = self.stats(duration).map(|stats| stats.to_json());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(gauge1: u32, gauge2: u32, gauge3: u32) -> Identity {
+        Identity { gauge1, gauge2, gauge3 }
+    }
+
+    #[test]
+    fn should_count_arcanist_card_draws() {
+        let mut tracker = IdentityTracker::new(Class::Arcanist, 1000);
+        tracker.on_identity(&identity(0, 0, 0), 0);
+        tracker.on_identity(&identity(500, 3, 0), 2000);
+        tracker.on_identity(&identity(500, 3, 7), 3000);
+        tracker.on_identity(&identity(1000, 0, 7), 4000);
+        tracker.on_identity(&identity(1000, 3, 7), 6000);
+
+        let Some(IdentityStats::Arcanist(stats)) = tracker.stats(10_000) else {
+            panic!("expected arcanist stats");
+        };
+
+        assert_eq!(stats.card_draws, HashMap::from([(3, 2), (7, 1)]));
+        assert_eq!(stats.log[1], (2, (50.0, 3, 0)));
+        // 0% for 2s, 50% for 2s, 100% for 6s
+        assert_eq!(stats.average, 70.0);
+    }
+
+    #[test]
+    fn should_track_bard_bubbles() {
+        let mut tracker = IdentityTracker::new(Class::Bard, 1000);
+        tracker.on_identity(&identity(1000, 2, 0), 1500);
+
+        let Some(IdentityStats::ArtistBard(stats)) = tracker.stats(3000) else {
+            panic!("expected artist/bard stats");
+        };
+
+        assert_eq!(stats.log, [(1, (100.0, 2))]);
+        assert_eq!(stats.average, 100.0);
+    }
+
+    #[test]
+    fn should_serialize_generic_stats_into_skill_stats() {
+        let mut tracker = IdentityTracker::new(Class::Berserker, 200);
+        let mut skill_stats = SkillStats::default();
+        tracker.write_to(&mut skill_stats, 4000);
+        assert!(skill_stats.identity_stats.is_none());
+
+        tracker.on_identity(&identity(50, 0, 0), 0);
+        tracker.on_identity(&identity(200, 0, 0), 2000);
+        tracker.write_to(&mut skill_stats, 4000);
+
+        let json = skill_stats.identity_stats.unwrap();
+        assert_eq!(json, r#"{"log":[[0,25.0],[2,100.0]],"average":62.5}"#);
+
+        let Ok(IdentityStats::Generic(parsed)) = IdentityStats::from_json(Class::Berserker, &json) else {
+            panic!("expected generic stats");
+        };
+        assert_eq!(parsed.average, 62.5);
+    }
+}
@@ -0,0 +1,462 @@
+use hashbrown::HashSet;
+
+use crate::models::EntityType;
+
+use super::MeterEvent;
+
+/// Milliseconds without damage after which a fight is considered over.
+pub const DEFAULT_OUT_OF_COMBAT_TIMEOUT: i64 = 30_000;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LifecyclePhase {
+    /// No fight in progress.
+    #[default]
+    Idle,
+    InCombat,
+    /// Stopped by the `pause_session` shortcut. Combat events are ignored until resumed.
+    Paused,
+    /// No damage for the out-of-combat timeout, or the zone changed mid-fight.
+    Ended,
+    /// The raid reported a failed attempt.
+    Wiped,
+    Cleared,
+    /// The ended fight was persisted.
+    Saved,
+}
+
+impl LifecyclePhase {
+    /// Whether a fight ended and can be saved.
+    pub fn is_finished(self) -> bool {
+        matches!(self, LifecyclePhase::Ended | LifecyclePhase::Wiped | LifecyclePhase::Cleared)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransitionCause {
+    Damage,
+    Timeout,
+    ZoneChange,
+    RaidResult,
+    Reset,
+    Pause,
+    Resume,
+    Save,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transition {
+    pub from: LifecyclePhase,
+    pub to: LifecyclePhase,
+    pub cause: TransitionCause,
+    /// Milliseconds since the unix epoch. For timeouts, the moment the timeout elapsed.
+    pub timestamp: i64,
+}
+
+type TransitionCallback = Box<dyn FnMut(&Transition) + Send>;
+
+/// Decides when a fight starts, pauses, ends and resets: idle → in combat → ended/wiped/cleared → saved.
+///
+/// Consumers feed it the same events as the [`MeterDriver`](super::MeterDriver), plus clock ticks and
+/// shortcuts, and react to the reported [`Transition`]s, e.g. by saving or resetting the encounter.
+pub struct EncounterLifecycle {
+    phase: LifecyclePhase,
+    paused_from: LifecyclePhase,
+    out_of_combat_timeout: i64,
+    boss_only_damage: bool,
+    bosses: HashSet<u64>,
+    fight_start: i64,
+    last_combat: i64,
+    callbacks: Vec<TransitionCallback>,
+}
+
+impl Default for EncounterLifecycle {
+    fn default() -> Self {
+        Self {
+            phase: LifecyclePhase::Idle,
+            paused_from: LifecyclePhase::Idle,
+            out_of_combat_timeout: DEFAULT_OUT_OF_COMBAT_TIMEOUT,
+            boss_only_damage: false,
+            bosses: HashSet::new(),
+            fight_start: 0,
+            last_combat: 0,
+            callbacks: Vec::new(),
+        }
+    }
+}
+
+impl EncounterLifecycle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn out_of_combat_timeout(mut self, milliseconds: i64) -> Self {
+        self.out_of_combat_timeout = milliseconds;
+        self
+    }
+
+    /// When enabled, only damage to a [`EntityType::Boss`] starts a fight or keeps it going.
+    pub fn boss_only_damage(mut self, enabled: bool) -> Self {
+        self.boss_only_damage = enabled;
+        self
+    }
+
+    /// Registers a callback invoked after every transition, in registration order.
+    pub fn on_transition(mut self, callback: impl FnMut(&Transition) + Send + 'static) -> Self {
+        self.callbacks.push(Box::new(callback));
+        self
+    }
+
+    pub fn phase(&self) -> LifecyclePhase {
+        self.phase
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.phase == LifecyclePhase::Paused
+    }
+
+    /// Start of the current or last fight, `0` before any fight.
+    pub fn fight_start(&self) -> i64 {
+        self.fight_start
+    }
+
+    pub fn last_combat(&self) -> i64 {
+        self.last_combat
+    }
+
+    /// Applies an event and returns whether it should be forwarded to the driver.
+    ///
+    /// While paused, combat events (damage, skill starts, stagger and identity) are not forwarded;
+    /// entity, party, status effect and zone events still are, so ids stay up to date.
+    /// Damage after a finished fight starts a new one; callers should save or reset the
+    /// encounter on the reported transition before forwarding the event.
+    pub fn handle(&mut self, event: &MeterEvent) -> bool {
+        self.track_bosses(event);
+
+        if self.is_paused() {
+            if matches!(
+                event,
+                MeterEvent::Damage(_)
+                    | MeterEvent::SkillStart { .. }
+                    | MeterEvent::Stagger { .. }
+                    | MeterEvent::Identity { .. }
+            ) {
+                return false;
+            }
+
+            // the fight ends while paused, and stays ended when resuming
+            if self.paused_from == LifecyclePhase::InCombat {
+                if let Some((phase, _)) = fight_end(event) {
+                    self.paused_from = phase;
+                }
+            }
+            return true;
+        }
+
+        let timestamp = event.timestamp();
+        if timestamp != 0 {
+            self.tick(timestamp);
+        }
+
+        match event {
+            MeterEvent::Damage(record) if record.damage > 0 => {
+                if self.boss_only_damage && !self.bosses.contains(&record.target_id) {
+                    return true;
+                }
+
+                if self.phase != LifecyclePhase::InCombat {
+                    self.fight_start = timestamp;
+                    self.transition(LifecyclePhase::InCombat, TransitionCause::Damage, timestamp);
+                }
+                self.last_combat = timestamp;
+            }
+            _ if self.phase == LifecyclePhase::InCombat => {
+                if let Some((phase, cause)) = fight_end(event) {
+                    self.transition(phase, cause, timestamp);
+                }
+            }
+            _ => {}
+        }
+
+        true
+    }
+
+    fn track_bosses(&mut self, event: &MeterEvent) {
+        match event {
+            MeterEvent::NewPlayer(entity) | MeterEvent::NewNpc(entity) | MeterEvent::NewSummon(entity) => {
+                if entity.entity_type == EntityType::Boss {
+                    self.bosses.insert(entity.id);
+                } else {
+                    self.bosses.remove(&entity.id);
+                }
+            }
+            MeterEvent::Despawn { entity_id, .. } => {
+                self.bosses.remove(entity_id);
+            }
+            MeterEvent::ZoneChange { .. } => self.bosses.clear(),
+            _ => {}
+        }
+    }
+
+    /// Ends the fight once `now` is past the out-of-combat timeout.
+    pub fn tick(&mut self, now: i64) {
+        if self.phase != LifecyclePhase::InCombat {
+            return;
+        }
+
+        let timeout_at = self.last_combat + self.out_of_combat_timeout;
+        if now >= timeout_at {
+            self.transition(LifecyclePhase::Ended, TransitionCause::Timeout, timeout_at);
+        }
+    }
+
+    /// The `reset_session` shortcut: drops the current fight, even when paused.
+    pub fn reset(&mut self, timestamp: i64) {
+        self.fight_start = 0;
+        self.last_combat = 0;
+        self.paused_from = LifecyclePhase::Idle;
+        if self.phase != LifecyclePhase::Idle {
+            self.transition(LifecyclePhase::Idle, TransitionCause::Reset, timestamp);
+        }
+    }
+
+    /// The `pause_session` shortcut. Time spent paused does not count towards the timeout.
+    pub fn toggle_pause(&mut self, timestamp: i64) {
+        if self.is_paused() {
+            let resumed = self.paused_from;
+            if resumed == LifecyclePhase::InCombat {
+                self.last_combat = self.last_combat.max(timestamp);
+            }
+            self.transition(resumed, TransitionCause::Resume, timestamp);
+        } else {
+            self.tick(timestamp);
+            self.paused_from = self.phase;
+            self.transition(LifecyclePhase::Paused, TransitionCause::Pause, timestamp);
+        }
+    }
+
+    /// Marks a finished fight as persisted. Returns `false` if no fight was finished.
+    pub fn mark_saved(&mut self, timestamp: i64) -> bool {
+        if !self.phase.is_finished() {
+            return false;
+        }
+
+        self.transition(LifecyclePhase::Saved, TransitionCause::Save, timestamp);
+        true
+    }
+
+    fn transition(&mut self, to: LifecyclePhase, cause: TransitionCause, timestamp: i64) {
+        let transition = Transition {
+            from: self.phase,
+            to,
+            cause,
+            timestamp,
+        };
+        self.phase = to;
+
+        for callback in &mut self.callbacks {
+            callback(&transition);
+        }
+    }
+}
+
+/// Phase a fight in progress ends in because of `event`, if any.
+fn fight_end(event: &MeterEvent) -> Option<(LifecyclePhase, TransitionCause)> {
+    match event {
+        MeterEvent::ZoneChange { .. } => Some((LifecyclePhase::Ended, TransitionCause::ZoneChange)),
+        MeterEvent::RaidResult { cleared: true, .. } => Some((LifecyclePhase::Cleared, TransitionCause::RaidResult)),
+        MeterEvent::RaidResult { cleared: false, .. } => Some((LifecyclePhase::Wiped, TransitionCause::RaidResult)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::meter::DamageRecord;
+    use crate::models::Entity;
+
+    use super::*;
+
+    use LifecyclePhase::*;
+
+    const START: i64 = 1_700_000_000_000;
+
+    fn damage(at: i64) -> MeterEvent {
+        MeterEvent::Damage(DamageRecord {
+            source_id: 1,
+            target_id: 9,
+            damage: 1000,
+            timestamp: START + at,
+            ..Default::default()
+        })
+    }
+
+    fn recording_lifecycle() -> (EncounterLifecycle, Arc<Mutex<Vec<Transition>>>) {
+        let transitions = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&transitions);
+        let lifecycle = EncounterLifecycle::new()
+            .out_of_combat_timeout(10_000)
+            .on_transition(move |transition| recorded.lock().unwrap().push(*transition));
+
+        (lifecycle, transitions)
+    }
+
+    fn phases(transitions: &Mutex<Vec<Transition>>) -> Vec<(LifecyclePhase, LifecyclePhase, i64)> {
+        transitions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|transition| (transition.from, transition.to, transition.timestamp - START))
+            .collect()
+    }
+
+    #[test]
+    fn should_end_fight_after_out_of_combat_timeout() {
+        let (mut lifecycle, transitions) = recording_lifecycle();
+
+        lifecycle.handle(&damage(0));
+        lifecycle.handle(&damage(5_000));
+        lifecycle.tick(START + 14_000);
+        assert_eq!(lifecycle.phase(), InCombat);
+
+        lifecycle.tick(START + 16_000);
+        assert!(lifecycle.mark_saved(START + 17_000));
+        assert!(!lifecycle.mark_saved(START + 18_000));
+        lifecycle.handle(&damage(20_000));
+
+        assert_eq!(
+            phases(&transitions),
+            [(Idle, InCombat, 0), (InCombat, Ended, 15_000), (Ended, Saved, 17_000), (Saved, InCombat, 20_000)]
+        );
+        assert_eq!(lifecycle.fight_start(), START + 20_000);
+    }
+
+    #[test]
+    fn should_detect_timeout_from_late_events() {
+        let (mut lifecycle, transitions) = recording_lifecycle();
+
+        lifecycle.handle(&damage(0));
+        lifecycle.handle(&damage(30_000));
+
+        assert_eq!(
+            phases(&transitions),
+            [(Idle, InCombat, 0), (InCombat, Ended, 10_000), (Ended, InCombat, 30_000)]
+        );
+        assert_eq!(transitions.lock().unwrap()[1].cause, TransitionCause::Timeout);
+    }
+
+    #[test]
+    fn should_report_raid_results_and_zone_changes() {
+        let (mut lifecycle, transitions) = recording_lifecycle();
+
+        lifecycle.handle(&damage(0));
+        lifecycle.handle(&MeterEvent::RaidResult {
+            cleared: false,
+            timestamp: START + 1_000,
+        });
+        lifecycle.handle(&damage(2_000));
+        lifecycle.handle(&MeterEvent::RaidResult {
+            cleared: true,
+            timestamp: START + 3_000,
+        });
+        lifecycle.handle(&MeterEvent::ZoneChange { timestamp: START + 4_000 });
+        lifecycle.handle(&damage(5_000));
+        lifecycle.handle(&MeterEvent::ZoneChange { timestamp: START + 6_000 });
+
+        assert_eq!(
+            phases(&transitions),
+            [
+                (Idle, InCombat, 0),
+                (InCombat, Wiped, 1_000),
+                (Wiped, InCombat, 2_000),
+                (InCombat, Cleared, 3_000),
+                (Cleared, InCombat, 5_000),
+                (InCombat, Ended, 6_000),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_ignore_combat_and_timeout_while_paused() {
+        let (mut lifecycle, transitions) = recording_lifecycle();
+
+        lifecycle.handle(&damage(0));
+        lifecycle.toggle_pause(START + 5_000);
+        assert!(!lifecycle.handle(&damage(6_000)));
+        lifecycle.tick(START + 60_000);
+        lifecycle.toggle_pause(START + 60_000);
+        lifecycle.tick(START + 65_000);
+
+        assert_eq!(lifecycle.phase(), InCombat);
+        assert_eq!(lifecycle.last_combat(), START + 60_000);
+        assert_eq!(
+            phases(&transitions),
+            [(Idle, InCombat, 0), (InCombat, Paused, 5_000), (Paused, InCombat, 60_000)]
+        );
+    }
+
+    #[test]
+    fn should_forward_zone_changes_while_paused() {
+        let (mut lifecycle, transitions) = recording_lifecycle();
+
+        lifecycle.handle(&damage(0));
+        lifecycle.toggle_pause(START + 1_000);
+        assert!(lifecycle.handle(&MeterEvent::ZoneChange { timestamp: START + 2_000 }));
+        assert!(lifecycle.handle(&MeterEvent::NewPlayer(Entity {
+            id: 7,
+            entity_type: EntityType::Player,
+            ..Default::default()
+        })));
+        assert!(!lifecycle.handle(&damage(3_000)));
+        lifecycle.toggle_pause(START + 4_000);
+
+        assert_eq!(lifecycle.phase(), Ended);
+        assert_eq!(
+            phases(&transitions),
+            [(Idle, InCombat, 0), (InCombat, Paused, 1_000), (Paused, Ended, 4_000)]
+        );
+    }
+
+    #[test]
+    fn should_only_start_on_boss_damage_in_boss_only_mode() {
+        let (lifecycle, transitions) = recording_lifecycle();
+        let mut lifecycle = lifecycle.boss_only_damage(true);
+
+        assert!(lifecycle.handle(&damage(0)));
+        assert_eq!(lifecycle.phase(), Idle);
+
+        lifecycle.handle(&MeterEvent::NewNpc(Entity {
+            id: 9,
+            entity_type: EntityType::Boss,
+            ..Default::default()
+        }));
+        lifecycle.handle(&damage(1_000));
+        lifecycle.handle(&MeterEvent::Despawn {
+            entity_id: 9,
+            timestamp: START + 2_000,
+        });
+        lifecycle.handle(&damage(9_000));
+        lifecycle.tick(START + 11_000);
+
+        assert_eq!(phases(&transitions), [(Idle, InCombat, 1_000), (InCombat, Ended, 11_000)]);
+    }
+
+    #[test]
+    fn should_reset_session_from_any_phase() {
+        let (mut lifecycle, transitions) = recording_lifecycle();
+
+        lifecycle.reset(START);
+        lifecycle.handle(&damage(0));
+        lifecycle.toggle_pause(START + 1_000);
+        lifecycle.reset(START + 2_000);
+
+        assert_eq!(lifecycle.phase(), Idle);
+        assert_eq!(lifecycle.fight_start(), 0);
+        assert_eq!(transitions.lock().unwrap().last().unwrap().cause, TransitionCause::Reset);
+        assert_eq!(
+            phases(&transitions),
+            [(Idle, InCombat, 0), (InCombat, Paused, 1_000), (Paused, Idle, 2_000)]
+        );
+    }
+}
@@ -0,0 +1,29 @@
+mod game_data_provider;
+mod encounter_state;
+mod status_effect_registry;
+mod rdps;
+mod boss_hp;
+mod stagger;
+mod identity;
+mod dps_series;
+mod entity_tracker;
+mod party;
+mod event;
+mod driver;
+mod lifecycle;
+mod recording;
+
+pub use game_data_provider::*;
+pub use encounter_state::*;
+pub use status_effect_registry::*;
+pub use rdps::*;
+pub use boss_hp::*;
+pub use stagger::*;
+pub use identity::*;
+pub use dps_series::*;
+pub use entity_tracker::*;
+pub use party::*;
+pub use event::*;
+pub use driver::*;
+pub use lifecycle::*;
+pub use recording::*;
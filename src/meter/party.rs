@@ -0,0 +1,293 @@
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Encounter, Entity};
+
+/// A member listed in a party update. The name may be empty while the member is out of range.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PartyMember {
+    pub character_id: u64,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Membership {
+    party_instance_id: u32,
+    /// Order in which the member joined, to keep the member list stable.
+    joined: u64,
+}
+
+/// Follows the parties of the current raid and builds [`EncounterMisc::party_info`](crate::models::EncounterMisc).
+///
+/// Parties are numbered in the order they were first seen and keep their index for the whole raid,
+/// even when they empty out. Members are keyed by character id, since entity ids change between zones.
+#[derive(Debug, Default, Clone)]
+pub struct PartyTracker {
+    raid_instance_id: Option<u32>,
+    party_indices: HashMap<u32, i32>,
+    members: HashMap<u64, Membership>,
+    names: HashMap<u64, String>,
+    local_character_id: Option<u64>,
+    next_join: u64,
+}
+
+impl PartyTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remembers the name of a player, used for members reported without one.
+    pub fn on_player(&mut self, entity: &Entity) {
+        if entity.character_id == 0 {
+            return;
+        }
+
+        if entity.is_local_player {
+            self.local_character_id = Some(entity.character_id);
+        }
+
+        if !entity.name.is_empty() {
+            self.names.insert(entity.character_id, entity.name.clone());
+        }
+    }
+
+    /// Ingests the full member list of a party.
+    ///
+    /// Members missing from the list have left it; disconnected members are still listed and kept.
+    /// An update for another raid instance starts over with the new raid.
+    pub fn on_party_info(&mut self, party_instance_id: u32, raid_instance_id: u32, members: &[PartyMember]) {
+        if self.raid_instance_id.is_some_and(|current| current != raid_instance_id) {
+            self.clear();
+        }
+        self.raid_instance_id = Some(raid_instance_id);
+
+        let next_index = self.party_indices.len() as i32;
+        self.party_indices.entry(party_instance_id).or_insert(next_index);
+
+        self.members.retain(|character_id, membership| {
+            membership.party_instance_id != party_instance_id
+                || members.iter().any(|member| member.character_id == *character_id)
+        });
+
+        for member in members {
+            if member.character_id == 0 {
+                continue;
+            }
+
+            if !member.name.is_empty() {
+                self.names.insert(member.character_id, member.name.clone());
+            }
+
+            let joined = self.next_join;
+            let membership = self.members.entry(member.character_id).or_insert(Membership {
+                party_instance_id,
+                joined,
+            });
+
+            if membership.joined == joined || membership.party_instance_id != party_instance_id {
+                membership.party_instance_id = party_instance_id;
+                membership.joined = joined;
+                self.next_join += 1;
+            }
+        }
+    }
+
+    /// A member left a party. When the local player leaves, the whole raid is forgotten.
+    pub fn on_party_leave(&mut self, party_instance_id: u32, character_id: u64) {
+        if self.local_character_id == Some(character_id) {
+            self.clear();
+            return;
+        }
+
+        if self
+            .members
+            .get(&character_id)
+            .is_some_and(|membership| membership.party_instance_id == party_instance_id)
+        {
+            self.members.remove(&character_id);
+        }
+    }
+
+    /// Index of the party a character is in.
+    pub fn party_of(&self, character_id: u64) -> Option<i32> {
+        let membership = self.members.get(&character_id)?;
+        self.party_indices.get(&membership.party_instance_id).copied()
+    }
+
+    /// Member names per party index, in join order. Members whose name is unknown are left out.
+    pub fn party_info(&self) -> HashMap<i32, Vec<String>> {
+        let mut members: Vec<(i32, u64, &str)> = self
+            .members
+            .iter()
+            .filter_map(|(character_id, membership)| {
+                let index = *self.party_indices.get(&membership.party_instance_id)?;
+                let name = self.names.get(character_id)?;
+                Some((index, membership.joined, name.as_str()))
+            })
+            .collect();
+        members.sort_unstable();
+
+        let mut parties: HashMap<i32, Vec<String>> = HashMap::new();
+        for (index, _, name) in members {
+            parties.entry(index).or_default().push(name.to_string());
+        }
+        parties
+    }
+
+    /// Sets `party_info` to the parties of members who appear in the encounter.
+    ///
+    /// Parties left without such members are dropped and the rest renumbered from 0 in index order,
+    /// so the written indices have no gaps.
+    pub fn write_to(&self, encounter: &mut Encounter) {
+        let mut parties: Vec<(i32, Vec<String>)> = self.party_info().into_iter().collect();
+        parties.sort_unstable_by_key(|(index, _)| *index);
+
+        let parties: HashMap<i32, Vec<String>> = parties
+            .into_iter()
+            .map(|(_, mut names)| {
+                names.retain(|name| encounter.entities.contains_key(name));
+                names
+            })
+            .filter(|names| !names.is_empty())
+            .enumerate()
+            .map(|(index, names)| (index as i32, names))
+            .collect();
+
+        if parties.is_empty() {
+            return;
+        }
+
+        encounter
+            .encounter_damage_stats
+            .misc
+            .get_or_insert_with(Default::default)
+            .party_info = Some(parties);
+    }
+
+    /// Forgets all parties. Known names are kept.
+    pub fn clear(&mut self) {
+        self.raid_instance_id = None;
+        self.party_indices.clear();
+        self.members.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::EncounterEntity;
+
+    use super::*;
+
+    fn member(character_id: u64, name: &str) -> PartyMember {
+        PartyMember {
+            character_id,
+            name: name.to_string(),
+        }
+    }
+
+    fn party(tracker: &PartyTracker, index: i32) -> Vec<String> {
+        tracker.party_info().remove(&index).unwrap_or_default()
+    }
+
+    #[test]
+    fn should_keep_party_indices_stable() {
+        let mut tracker = PartyTracker::new();
+        tracker.on_party_info(20, 1, &[member(1, "Alice"), member(2, "Bob")]);
+        tracker.on_party_info(10, 1, &[member(3, "Charlie")]);
+        tracker.on_party_info(20, 1, &[member(2, "Bob"), member(1, "Alice"), member(4, "Dave")]);
+
+        assert_eq!(party(&tracker, 0), ["Alice", "Bob", "Dave"]);
+        assert_eq!(party(&tracker, 1), ["Charlie"]);
+
+        tracker.on_party_leave(10, 3);
+        tracker.on_party_info(30, 1, &[member(5, "Eve")]);
+
+        assert_eq!(tracker.party_of(5), Some(2));
+        assert!(!tracker.party_info().contains_key(&1));
+    }
+
+    #[test]
+    fn should_move_members_between_parties() {
+        let mut tracker = PartyTracker::new();
+        tracker.on_party_info(20, 1, &[member(1, "Alice"), member(2, "Bob")]);
+        tracker.on_party_info(10, 1, &[member(3, "Charlie"), member(2, "Bob")]);
+        tracker.on_party_info(20, 1, &[member(1, "Alice")]);
+
+        assert_eq!(party(&tracker, 0), ["Alice"]);
+        assert_eq!(party(&tracker, 1), ["Charlie", "Bob"]);
+
+        // a stale leave for the old party does not remove Bob from his new one
+        tracker.on_party_leave(20, 2);
+        assert_eq!(tracker.party_of(2), Some(1));
+    }
+
+    #[test]
+    fn should_resolve_names_of_members_out_of_range() {
+        let mut tracker = PartyTracker::new();
+        tracker.on_party_info(20, 1, &[member(1, "Alice"), member(2, "")]);
+        assert_eq!(party(&tracker, 0), ["Alice"]);
+
+        tracker.on_player(&Entity {
+            character_id: 2,
+            name: "Bob".to_string(),
+            ..Default::default()
+        });
+        assert_eq!(party(&tracker, 0), ["Alice", "Bob"]);
+    }
+
+    #[test]
+    fn should_reset_on_new_raid_or_local_leave() {
+        let mut tracker = PartyTracker::new();
+        tracker.on_player(&Entity {
+            character_id: 1,
+            name: "Alice".to_string(),
+            is_local_player: true,
+            ..Default::default()
+        });
+        tracker.on_party_info(20, 1, &[member(1, "Alice"), member(2, "Bob")]);
+        tracker.on_party_info(10, 2, &[member(1, ""), member(3, "Charlie")]);
+
+        assert_eq!(party(&tracker, 0), ["Alice", "Charlie"]);
+        assert!(tracker.party_of(2).is_none());
+
+        tracker.on_party_leave(10, 1);
+        assert!(tracker.party_info().is_empty());
+    }
+
+    #[test]
+    fn should_write_members_present_in_encounter() {
+        let mut tracker = PartyTracker::new();
+        let mut encounter = Encounter::default();
+        tracker.write_to(&mut encounter);
+        assert!(encounter.encounter_damage_stats.misc.is_none());
+
+        tracker.on_party_info(20, 1, &[member(1, "Alice"), member(2, "Bob")]);
+        tracker.on_party_info(10, 1, &[member(3, "Charlie")]);
+        encounter.entities.insert("Bob".to_string(), EncounterEntity::default());
+        encounter.entities.insert("Charlie".to_string(), EncounterEntity::default());
+        tracker.write_to(&mut encounter);
+
+        let party_info = encounter.encounter_damage_stats.misc.unwrap().party_info.unwrap();
+        assert_eq!(party_info[&0], ["Bob"]);
+        assert_eq!(party_info[&1], ["Charlie"]);
+    }
+
+    #[test]
+    fn should_renumber_parties_without_members_in_encounter() {
+        let mut tracker = PartyTracker::new();
+        let mut encounter = Encounter::default();
+        tracker.on_party_info(20, 1, &[member(1, "Alice")]);
+        tracker.on_party_info(10, 1, &[member(2, "Bob")]);
+        tracker.on_party_info(30, 1, &[member(3, "Charlie")]);
+        encounter.entities.insert("Alice".to_string(), EncounterEntity::default());
+        encounter.entities.insert("Charlie".to_string(), EncounterEntity::default());
+
+        tracker.write_to(&mut encounter);
+
+        let party_info = encounter.encounter_damage_stats.misc.unwrap().party_info.unwrap();
+        assert_eq!(party_info.len(), 2);
+        assert_eq!(party_info[&0], ["Alice"]);
+        assert_eq!(party_info[&1], ["Charlie"]);
+    }
+}
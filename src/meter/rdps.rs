@@ -0,0 +1,475 @@
+use hashbrown::{HashMap, HashSet};
+
+use crate::models::*;
+
+/// How a buff raises the damage of the hits it is active on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Synergy {
+    /// Independent damage multiplier, `0.1` for +10% damage (also brands and damage-taken debuffs).
+    DamageMultiplier(f64),
+    /// Additive attack power increase, `0.15` for +15% attack power.
+    AttackPower(f64),
+    /// Additive crit rate increase, `0.1` for +10% crit rate.
+    CritRate(f64),
+}
+
+/// A buff granted by `source`, typically a support or a DPS with a party synergy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuffContribution {
+    /// Name of the entity which applies the buff, as keyed in `Encounter::entities`.
+    pub source: String,
+    /// Skill of `source` credited with `rdps_damage_given`.
+    pub skill_id: Option<u32>,
+    pub synergy: Synergy,
+}
+
+/// Stats of the receiving player needed to weigh attack power and crit buffs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayerSynergyStats {
+    pub crit_rate: f64,
+    pub crit_damage: f64,
+    /// Attack power bonus from the player's own sources, `0.3` for +30%.
+    pub attack_power_bonus: f64,
+}
+
+impl Default for PlayerSynergyStats {
+    fn default() -> Self {
+        Self {
+            crit_rate: 0.0,
+            crit_damage: 2.0,
+            attack_power_bonus: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Given {
+    total: i64,
+    by_skill: HashMap<u32, i64>,
+}
+
+/// Attributes buffed damage back to the players who granted the buffs.
+#[derive(Debug, Default, Clone)]
+pub struct RdpsCalculator {
+    contributions: HashMap<u32, BuffContribution>,
+    players: HashMap<String, PlayerSynergyStats>,
+}
+
+impl RdpsCalculator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_contribution(&mut self, buff_id: u32, contribution: BuffContribution) {
+        self.contributions.insert(buff_id, contribution);
+    }
+
+    pub fn set_player_stats(&mut self, name: impl Into<String>, stats: PlayerSynergyStats) {
+        self.players.insert(name.into(), stats);
+    }
+
+    /// Recomputes every rdps field of the encounter and records in `EncounterMisc`
+    /// whether the inputs were sufficient. Returns the resulting `rdps_valid`.
+    pub fn apply(&self, encounter: &mut Encounter) -> bool {
+        let mut given: HashMap<String, Given> = HashMap::new();
+        let mut missing_buffs: HashSet<u32> = HashSet::new();
+        let mut missing_stats: HashSet<String> = HashSet::new();
+        let mut has_player_damage = false;
+
+        let supports: HashSet<String> = encounter
+            .entities
+            .iter()
+            .filter(|(_, entity)| Class::try_from(entity.class_id).is_ok_and(|class| class.is_support()))
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        let damage_buffs: HashSet<u32> = encounter
+            .encounter_damage_stats
+            .buffs
+            .iter()
+            .chain(&encounter.encounter_damage_stats.debuffs)
+            .filter(|(_, status_effect)| is_party_damage_synergy(status_effect))
+            .map(|(id, _)| *id)
+            .collect();
+
+        for (name, entity) in encounter.entities.iter_mut() {
+            reset_rdps(entity);
+
+            if entity.entity_type != EntityType::Player {
+                continue;
+            }
+
+            let stats = self.players.get(name).copied();
+
+            for skill in entity.skills.values_mut() {
+                for hit in skill.skill_cast_log.iter_mut().flat_map(|cast| &mut cast.hits) {
+                    has_player_damage |= hit.damage > 0;
+
+                    let buff_ids = hit.buffed_by.iter().chain(&hit.debuffed_by);
+                    let mut active: Vec<(u32, &BuffContribution)> = Vec::new();
+
+                    for buff_id in buff_ids {
+                        match self.contributions.get(buff_id) {
+                            Some(contribution)
+                                if contribution.source != *name
+                                    && !active.iter().any(|(id, _)| id == buff_id) =>
+                            {
+                                active.push((*buff_id, contribution));
+                            }
+                            Some(_) => {}
+                            None if damage_buffs.contains(buff_id) => {
+                                missing_buffs.insert(*buff_id);
+                            }
+                            None => {}
+                        }
+                    }
+
+                    let needs_stats = active
+                        .iter()
+                        .any(|(_, contribution)| !matches!(contribution.synergy, Synergy::DamageMultiplier(_)));
+                    if needs_stats && stats.is_none() {
+                        missing_stats.insert(name.clone());
+                    }
+
+                    let shares = split_hit(hit.damage, &active, stats.unwrap_or_default());
+
+                    for ((_, contribution), share) in active.iter().zip(shares) {
+                        let source_is_support = supports.contains(&contribution.source);
+                        let share = share.round() as i64;
+
+                        hit.rdps_damage_received += share;
+                        skill.rdps_damage_received += share;
+                        entity.damage_stats.rdps_damage_received += share;
+
+                        if source_is_support {
+                            hit.rdps_damage_received_support += share;
+                            skill.rdps_damage_received_support += share;
+                            entity.damage_stats.rdps_damage_received_support += share;
+                        }
+
+                        let source = given.entry(contribution.source.clone()).or_default();
+                        source.total += share;
+                        if let Some(skill_id) = contribution.skill_id {
+                            *source.by_skill.entry(skill_id).or_default() += share;
+                        }
+                    }
+                }
+            }
+        }
+
+        for (name, given) in given {
+            let Some(entity) = encounter.entities.get_mut(&name) else {
+                continue;
+            };
+
+            entity.damage_stats.rdps_damage_given += given.total;
+            for (skill_id, amount) in given.by_skill {
+                if let Some(skill) = entity.skills.get_mut(&skill_id) {
+                    skill.rdps_damage_given += amount;
+                }
+            }
+        }
+
+        let mut unknown_sources: Vec<&str> = self
+            .contributions
+            .values()
+            .map(|contribution| contribution.source.as_str())
+            .filter(|source| !encounter.entities.contains_key(*source))
+            .collect();
+        unknown_sources.sort_unstable();
+        unknown_sources.dedup();
+
+        let message = if !has_player_damage {
+            Some("no player damage with hit logs".to_string())
+        } else if self.contributions.is_empty() {
+            Some("no buff contributions provided".to_string())
+        } else if !missing_buffs.is_empty() {
+            let mut ids: Vec<u32> = missing_buffs.into_iter().collect();
+            ids.sort_unstable();
+            Some(format!("missing contributions for buffs {:?}", ids))
+        } else if !missing_stats.is_empty() {
+            let mut names: Vec<String> = missing_stats.into_iter().collect();
+            names.sort();
+            Some(format!("missing player stats for {}", names.join(", ")))
+        } else if !unknown_sources.is_empty() {
+            Some(format!("buff sources not part of the encounter: {}", unknown_sources.join(", ")))
+        } else {
+            None
+        };
+
+        let misc = encounter
+            .encounter_damage_stats
+            .misc
+            .get_or_insert_with(EncounterMisc::default);
+        misc.rdps_valid = Some(message.is_none());
+        misc.rdps_message = message;
+
+        misc.rdps_valid == Some(true)
+    }
+}
+
+fn is_party_damage_synergy(status_effect: &StatusEffect) -> bool {
+    status_effect.target == StatusEffectTarget::PARTY
+        && status_effect.buff_type
+            & (StatusEffectBuffTypeFlags::DMG | StatusEffectBuffTypeFlags::CRIT).bits()
+            != 0
+        && matches!(status_effect.buff_category.as_str(), "classskill" | "identity" | "arkpassive")
+}
+
+fn reset_rdps(entity: &mut EncounterEntity) {
+    entity.damage_stats.rdps_damage_received = 0;
+    entity.damage_stats.rdps_damage_received_support = 0;
+    entity.damage_stats.rdps_damage_given = 0;
+
+    for skill in entity.skills.values_mut() {
+        skill.rdps_damage_received = 0;
+        skill.rdps_damage_received_support = 0;
+        skill.rdps_damage_given = 0;
+
+        for hit in skill.skill_cast_log.iter_mut().flat_map(|cast| &mut cast.hits) {
+            hit.rdps_damage_received = 0;
+            hit.rdps_damage_received_support = 0;
+        }
+    }
+}
+
+/// Splits the part of `damage` which exists only because of the active buffs.
+///
+/// Each buff is converted to the damage multiplier it caused; the buffed portion is
+/// `damage - damage / product(multipliers)` and is shared in proportion to the log of each multiplier,
+/// which keeps the split independent of the order the buffs are applied in.
+fn split_hit(damage: i64, active: &[(u32, &BuffContribution)], stats: PlayerSynergyStats) -> Vec<f64> {
+    if active.is_empty() || damage <= 0 {
+        return vec![0.0; active.len()];
+    }
+
+    let total_attack_power: f64 = active
+        .iter()
+        .filter_map(|(_, contribution)| match contribution.synergy {
+            Synergy::AttackPower(value) => Some(value),
+            _ => None,
+        })
+        .sum();
+    let total_crit_rate: f64 = active
+        .iter()
+        .filter_map(|(_, contribution)| match contribution.synergy {
+            Synergy::CritRate(value) => Some(value),
+            _ => None,
+        })
+        .sum();
+
+    let attack_power = 1.0 + stats.attack_power_bonus + total_attack_power;
+    let crit_rate = (stats.crit_rate + total_crit_rate).min(1.0);
+    let expected_crit = |rate: f64| 1.0 + rate.clamp(0.0, 1.0) * (stats.crit_damage - 1.0);
+
+    let multipliers: Vec<f64> = active
+        .iter()
+        .map(|(_, contribution)| match contribution.synergy {
+            Synergy::DamageMultiplier(value) => 1.0 + value,
+            // a bonus of -100% or less leaves nothing to scale, the synergy is skipped
+            Synergy::AttackPower(value) if attack_power <= 0.0 || attack_power - value <= 0.0 => 1.0,
+            Synergy::AttackPower(value) => attack_power / (attack_power - value),
+            Synergy::CritRate(value) => expected_crit(crit_rate) / expected_crit(crit_rate - value),
+        })
+        .map(|multiplier| multiplier.max(1.0))
+        .collect();
+
+    let log_total: f64 = multipliers.iter().map(|multiplier| multiplier.ln()).sum();
+    if log_total <= 0.0 {
+        return vec![0.0; active.len()];
+    }
+
+    let damage = damage as f64;
+    let buffed = damage - damage / log_total.exp();
+
+    multipliers
+        .iter()
+        .map(|multiplier| buffed * multiplier.ln() / log_total)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(name: &str, class: Class, hits: Vec<SkillHit>) -> EncounterEntity {
+        let skill = Skill {
+            id: 16140,
+            skill_cast_log: vec![SkillCast {
+                hits,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        EncounterEntity {
+            name: name.to_string(),
+            entity_type: EntityType::Player,
+            class_id: class as u32,
+            skills: HashMap::from([(16140, skill)]),
+            ..Default::default()
+        }
+    }
+
+    fn hit(damage: i64, buffed_by: Vec<u32>) -> SkillHit {
+        SkillHit {
+            damage,
+            buffed_by,
+            ..Default::default()
+        }
+    }
+
+    fn encounter(entities: Vec<EncounterEntity>) -> Encounter {
+        Encounter {
+            entities: entities.into_iter().map(|entity| (entity.name.clone(), entity)).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn bard_buff(synergy: Synergy) -> BuffContribution {
+        BuffContribution {
+            source: "Bard".to_string(),
+            skill_id: Some(21160),
+            synergy,
+        }
+    }
+
+    #[test]
+    fn should_attribute_damage_multiplier_to_support() {
+        let mut encounter = encounter(vec![
+            player("Alice", Class::Berserker, vec![hit(1100, vec![211601]), hit(1000, vec![])]),
+            player("Bard", Class::Bard, vec![]),
+        ]);
+        let mut calculator = RdpsCalculator::new();
+        calculator.add_contribution(211601, bard_buff(Synergy::DamageMultiplier(0.1)));
+
+        assert!(calculator.apply(&mut encounter));
+
+        let alice = &encounter.entities["Alice"];
+        let bard = &encounter.entities["Bard"];
+        assert_eq!(alice.damage_stats.rdps_damage_received, 100);
+        assert_eq!(alice.damage_stats.rdps_damage_received_support, 100);
+        assert_eq!(alice.skills[&16140].skill_cast_log[0].hits[0].rdps_damage_received, 100);
+        assert_eq!(bard.damage_stats.rdps_damage_given, 100);
+        assert_eq!(encounter.encounter_damage_stats.misc.unwrap().rdps_valid, Some(true));
+    }
+
+    #[test]
+    fn should_split_stacked_buffs_and_be_idempotent() {
+        let mut encounter = encounter(vec![
+            player("Alice", Class::Berserker, vec![hit(1210, vec![1, 2])]),
+            player("Bard", Class::Bard, vec![]),
+            player("Bob", Class::Deadeye, vec![]),
+        ]);
+        let mut calculator = RdpsCalculator::new();
+        calculator.add_contribution(1, bard_buff(Synergy::DamageMultiplier(0.1)));
+        calculator.add_contribution(
+            2,
+            BuffContribution {
+                source: "Bob".to_string(),
+                skill_id: Some(29360),
+                synergy: Synergy::DamageMultiplier(0.1),
+            },
+        );
+
+        calculator.apply(&mut encounter);
+        calculator.apply(&mut encounter);
+
+        let alice = &encounter.entities["Alice"];
+        assert_eq!(alice.damage_stats.rdps_damage_received, 210);
+        assert_eq!(alice.damage_stats.rdps_damage_received_support, 105);
+        assert_eq!(encounter.entities["Bard"].damage_stats.rdps_damage_given, 105);
+        assert_eq!(encounter.entities["Bob"].damage_stats.rdps_damage_given, 105);
+    }
+
+    #[test]
+    fn should_weigh_attack_power_and_crit_with_player_stats() {
+        let mut encounter = encounter(vec![
+            player("Alice", Class::Berserker, vec![hit(1500, vec![1]), hit(1500, vec![2])]),
+            player("Bard", Class::Bard, vec![]),
+        ]);
+        let mut calculator = RdpsCalculator::new();
+        calculator.add_contribution(1, bard_buff(Synergy::AttackPower(0.5)));
+        calculator.add_contribution(2, bard_buff(Synergy::CritRate(0.5)));
+        calculator.set_player_stats(
+            "Alice",
+            PlayerSynergyStats {
+                crit_rate: 0.0,
+                crit_damage: 2.0,
+                attack_power_bonus: 0.0,
+            },
+        );
+
+        assert!(calculator.apply(&mut encounter));
+
+        // +50% atk power on a 1.0 base and +50% crit with 200% crit damage are both 1.5x
+        assert_eq!(encounter.entities["Alice"].damage_stats.rdps_damage_received, 1000);
+    }
+
+    #[test]
+    fn should_skip_attack_power_without_base() {
+        let mut encounter = encounter(vec![
+            player("Alice", Class::Berserker, vec![hit(1500, vec![1])]),
+            player("Bard", Class::Bard, vec![]),
+        ]);
+        let mut calculator = RdpsCalculator::new();
+        calculator.add_contribution(1, bard_buff(Synergy::AttackPower(0.5)));
+        calculator.set_player_stats(
+            "Alice",
+            PlayerSynergyStats {
+                crit_rate: 0.0,
+                crit_damage: 2.0,
+                attack_power_bonus: -1.0,
+            },
+        );
+
+        calculator.apply(&mut encounter);
+
+        assert_eq!(encounter.entities["Alice"].damage_stats.rdps_damage_received, 0);
+        assert_eq!(encounter.entities["Bard"].damage_stats.rdps_damage_given, 0);
+    }
+
+    #[test]
+    fn should_list_unknown_sources_in_order() {
+        let mut encounter = encounter(vec![player("Alice", Class::Berserker, vec![hit(1000, vec![1, 2, 3])])]);
+        let mut calculator = RdpsCalculator::new();
+        for (buff_id, source) in [(1, "Zed"), (2, "Yan"), (3, "Zed")] {
+            calculator.add_contribution(
+                buff_id,
+                BuffContribution {
+                    source: source.to_string(),
+                    skill_id: None,
+                    synergy: Synergy::DamageMultiplier(0.1),
+                },
+            );
+        }
+
+        assert!(!calculator.apply(&mut encounter));
+        let misc = encounter.encounter_damage_stats.misc.as_ref().unwrap();
+        assert_eq!(misc.rdps_message.as_deref(), Some("buff sources not part of the encounter: Yan, Zed"));
+    }
+
+    #[test]
+    fn should_flag_missing_inputs() {
+        let mut encounter = encounter(vec![player("Alice", Class::Berserker, vec![hit(1000, vec![211601])])]);
+        encounter.encounter_damage_stats.buffs.insert(
+            211601,
+            StatusEffect {
+                target: StatusEffectTarget::PARTY,
+                buff_category: "classskill".to_string(),
+                buff_type: StatusEffectBuffTypeFlags::DMG.bits(),
+                ..Default::default()
+            },
+        );
+        let mut calculator = RdpsCalculator::new();
+
+        assert!(!calculator.apply(&mut encounter));
+        let misc = encounter.encounter_damage_stats.misc.as_ref().unwrap();
+        assert_eq!(misc.rdps_message.as_deref(), Some("no buff contributions provided"));
+
+        calculator.add_contribution(1, bard_buff(Synergy::DamageMultiplier(0.1)));
+        assert!(!calculator.apply(&mut encounter));
+        let misc = encounter.encounter_damage_stats.misc.as_ref().unwrap();
+        assert_eq!(misc.rdps_message.as_deref(), Some("missing contributions for buffs [211601]"));
+    }
+}
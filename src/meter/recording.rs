@@ -0,0 +1,277 @@
+use std::fmt::{self, Display, Formatter};
+use std::io::{BufRead, Lines, Write};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::Encounter;
+
+use super::{EncounterState, GameDataProvider, MeterDriver, MeterEvent};
+
+/// Version written to the header line of recordings.
+pub const RECORDING_VERSION: u32 = 1;
+
+/// First line of a recording: everything needed to rebuild the [`MeterDriver`] the events were fed to.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RecordingHeader {
+    pub version: u32,
+    pub local_player: String,
+    pub difficulty: Option<String>,
+    pub boss_only_damage: bool,
+    pub identity_max_gauge: Option<u32>,
+    /// Wall-clock start of the recording in milliseconds since the unix epoch.
+    pub started_at: i64,
+}
+
+impl RecordingHeader {
+    pub fn new(started_at: i64) -> Self {
+        Self {
+            version: RECORDING_VERSION,
+            started_at,
+            ..Default::default()
+        }
+    }
+
+    pub fn driver(&self, game_data: Arc<dyn GameDataProvider + Send + Sync>) -> MeterDriver {
+        let mut builder = EncounterState::builder()
+            .local_player(&self.local_player)
+            .boss_only_damage(self.boss_only_damage)
+            .game_data(game_data);
+        if let Some(difficulty) = &self.difficulty {
+            builder = builder.difficulty(difficulty);
+        }
+
+        let driver = MeterDriver::new(builder.build());
+        match self.identity_max_gauge {
+            Some(max_gauge) => driver.track_identity(max_gauge),
+            None => driver,
+        }
+    }
+}
+
+/// An event with the time it was received, in milliseconds after [`RecordingHeader::started_at`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    #[serde(rename = "t")]
+    pub received_at: i64,
+    #[serde(rename = "e")]
+    pub event: MeterEvent,
+}
+
+#[derive(Serialize)]
+struct RecordedEventRef<'a> {
+    t: i64,
+    e: &'a MeterEvent,
+}
+
+#[derive(Debug)]
+pub enum RecordingError {
+    Io(std::io::Error),
+    /// Malformed line, numbered from 1.
+    Json { line: usize, source: serde_json::Error },
+    MissingHeader,
+    UnsupportedVersion(u32),
+}
+
+impl Display for RecordingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordingError::Io(err) => write!(f, "{}", err),
+            RecordingError::Json { line, source } => write!(f, "line {}: {}", line, source),
+            RecordingError::MissingHeader => write!(f, "recording has no header"),
+            RecordingError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported recording version {}, expected 1 to {}",
+                version, RECORDING_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RecordingError {}
+
+impl From<std::io::Error> for RecordingError {
+    fn from(err: std::io::Error) -> Self {
+        RecordingError::Io(err)
+    }
+}
+
+/// Writes meter events as JSON lines, preceded by a [`RecordingHeader`] line.
+pub struct EventRecorder<W: Write> {
+    writer: W,
+    lines: usize,
+}
+
+impl<W: Write> EventRecorder<W> {
+    pub fn new(mut writer: W, header: &RecordingHeader) -> Result<Self, RecordingError> {
+        serde_json::to_writer(&mut writer, header).map_err(|source| RecordingError::Json { line: 1, source })?;
+        writer.write_all(b"\n")?;
+        Ok(Self { writer, lines: 1 })
+    }
+
+    pub fn record(&mut self, received_at: i64, event: &MeterEvent) -> Result<(), RecordingError> {
+        self.lines += 1;
+        let line = self.lines;
+        serde_json::to_writer(&mut self.writer, &RecordedEventRef { t: received_at, e: event })
+            .map_err(|source| RecordingError::Json { line, source })?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), RecordingError> {
+        Ok(self.writer.flush()?)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads a recording written by [`EventRecorder`], yielding its events in order.
+pub struct EventReader<R: BufRead> {
+    header: RecordingHeader,
+    lines: Lines<R>,
+    line: usize,
+}
+
+impl<R: BufRead> EventReader<R> {
+    pub fn new(reader: R) -> Result<Self, RecordingError> {
+        let mut lines = reader.lines();
+        let header = lines.next().ok_or(RecordingError::MissingHeader)??;
+        let header: RecordingHeader =
+            serde_json::from_str(&header).map_err(|source| RecordingError::Json { line: 1, source })?;
+
+        if header.version == 0 || header.version > RECORDING_VERSION {
+            return Err(RecordingError::UnsupportedVersion(header.version));
+        }
+
+        Ok(Self { header, lines, line: 1 })
+    }
+
+    pub fn header(&self) -> &RecordingHeader {
+        &self.header
+    }
+}
+
+impl<R: BufRead> Iterator for EventReader<R> {
+    type Item = Result<RecordedEvent, RecordingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err.into())),
+            };
+            self.line += 1;
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let line_number = self.line;
+            return Some(
+                serde_json::from_str(&line).map_err(|source| RecordingError::Json { line: line_number, source }),
+            );
+        }
+    }
+}
+
+/// Feeds a recording into a fresh [`MeterDriver`] and returns the finished encounter.
+///
+/// Replays of the same recording serialize to identical bytes with
+/// [`encounter_to_canonical_string`](crate::schema::encounter_to_canonical_string).
+pub fn replay<R: BufRead>(
+    reader: R,
+    game_data: Arc<dyn GameDataProvider + Send + Sync>,
+) -> Result<Encounter, RecordingError> {
+    let mut events = EventReader::new(reader)?;
+    let mut driver = events.header().driver(game_data);
+
+    for recorded in &mut events {
+        driver.handle(&recorded?.event);
+    }
+
+    Ok(driver.finish().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    use crate::schema::encounter_to_canonical_string;
+
+    use super::super::driver::tests::scripted_events;
+    use super::*;
+
+    fn header() -> RecordingHeader {
+        RecordingHeader {
+            identity_max_gauge: Some(1000),
+            difficulty: Some("Hard".to_string()),
+            ..RecordingHeader::new(1_700_000_000_000)
+        }
+    }
+
+    fn record(events: &[MeterEvent]) -> Vec<u8> {
+        let mut recorder = EventRecorder::new(Vec::new(), &header()).unwrap();
+        for (index, event) in events.iter().enumerate() {
+            recorder.record(index as i64 * 10, event).unwrap();
+        }
+        recorder.into_inner()
+    }
+
+    fn canonical(encounter: &Encounter) -> String {
+        encounter_to_canonical_string(encounter).unwrap()
+    }
+
+    #[test]
+    fn should_replay_recording_deterministically() {
+        let events = scripted_events();
+        let mut live = header().driver(Arc::new(()));
+        live.run(&events);
+        let expected = canonical(live.finish());
+
+        let recording = record(&events);
+        let first = replay(BufReader::new(recording.as_slice()), Arc::new(())).unwrap();
+        let second = replay(BufReader::new(recording.as_slice()), Arc::new(())).unwrap();
+
+        assert_eq!(canonical(&first), expected);
+        assert_eq!(canonical(&second), expected);
+        assert_eq!(first.difficulty.as_deref(), Some("Hard"));
+    }
+
+    #[test]
+    fn should_write_one_line_per_event() {
+        let events = scripted_events();
+
+        let recording = String::from_utf8(record(&events)).unwrap();
+
+        assert_eq!(recording.lines().count(), events.len() + 1);
+        let reader = EventReader::new(BufReader::new(recording.as_bytes())).unwrap();
+        assert_eq!(reader.header(), &header());
+        let received_at: Vec<i64> = reader.map(|event| event.unwrap().received_at).collect();
+        assert_eq!(received_at[..3], [0, 10, 20]);
+    }
+
+    #[test]
+    fn should_report_malformed_lines() {
+        let mut recording = record(&scripted_events()[..2]);
+        recording.extend_from_slice(b"{\"t\":0,\"e\":{\"unknown\":{}}}\n");
+
+        let result = replay(BufReader::new(recording.as_slice()), Arc::new(()));
+
+        assert!(matches!(result, Err(RecordingError::Json { line: 4, .. })));
+        assert!(matches!(
+            replay(BufReader::new(&b""[..]), Arc::new(())),
+            Err(RecordingError::MissingHeader)
+        ));
+        assert!(matches!(
+            replay(BufReader::new(&b"{\"version\":2}\n"[..]), Arc::new(())),
+            Err(RecordingError::UnsupportedVersion(2))
+        ));
+        assert!(matches!(
+            replay(BufReader::new(&b"{}\n"[..]), Arc::new(())),
+            Err(RecordingError::UnsupportedVersion(0))
+        ));
+    }
+}
@@ -0,0 +1,163 @@
+use crate::models::{EncounterDamageStats, Stagger, StaggerStats};
+
+/// A completed stagger break.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StaggerBreak {
+    /// Milliseconds after the fight started at which the bar was filled.
+    pub timestamp: i64,
+    /// Milliseconds it took to fill the bar.
+    pub duration: i64,
+}
+
+/// Follows the stagger bar of the current boss and derives [`StaggerStats`].
+#[derive(Debug, Default, Clone)]
+pub struct StaggerTracker {
+    log: Vec<(i32, f32)>,
+    breaks: Vec<StaggerBreak>,
+    stagger_start: Option<i64>,
+    max_stagger: u32,
+    previous: u32,
+}
+
+impl StaggerTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ingests a stagger update received `relative_timestamp` milliseconds after the fight started.
+    ///
+    /// A break is counted when the bar fills up; emptying it without a break discards the attempt.
+    pub fn on_stagger(&mut self, stagger: &Stagger, relative_timestamp: i64) {
+        if stagger.max == 0 {
+            return;
+        }
+
+        self.max_stagger = stagger.max;
+        let current = stagger.current.min(stagger.max);
+
+        if current >= stagger.max {
+            if self.previous < stagger.max {
+                let start = self.stagger_start.unwrap_or(relative_timestamp);
+                self.breaks.push(StaggerBreak {
+                    timestamp: relative_timestamp,
+                    duration: relative_timestamp - start,
+                });
+            }
+            self.stagger_start = None;
+        } else if current == 0 {
+            self.stagger_start = None;
+        } else if self.stagger_start.is_none() {
+            self.stagger_start = Some(relative_timestamp);
+        }
+
+        self.previous = current;
+
+        let second = (relative_timestamp / 1000) as i32;
+        let percent = (current as f64 * 100.0 / stagger.max as f64) as f32;
+        match self.log.last_mut() {
+            Some(last) if last.0 == second => last.1 = percent,
+            _ => self.log.push((second, percent)),
+        }
+    }
+
+    pub fn breaks(&self) -> &[StaggerBreak] {
+        &self.breaks
+    }
+
+    pub fn max_stagger(&self) -> u32 {
+        self.max_stagger
+    }
+
+    /// Builds the stats for a fight lasting `duration` milliseconds.
+    ///
+    /// `average` is the share of the bar, in percent, filled per second while staggering;
+    /// `staggers_per_min` counts breaks over the whole fight. `None` until any stagger was seen.
+    pub fn stats(&self, duration: i64) -> Option<StaggerStats> {
+        if self.log.is_empty() {
+            return None;
+        }
+
+        let stagger_time: i64 = self.breaks.iter().map(|stagger_break| stagger_break.duration).sum();
+        let average = if stagger_time > 0 {
+            self.breaks.len() as f64 / (stagger_time as f64 / 1000.0) * 100.0
+        } else {
+            0.0
+        };
+
+        let minutes = duration as f64 / 60_000.0;
+        let staggers_per_min = if minutes > 0.0 {
+            self.breaks.len() as f64 / minutes
+        } else {
+            0.0
+        };
+
+        Some(StaggerStats {
+            average,
+            staggers_per_min,
+            log: self.log.clone(),
+        })
+    }
+
+    pub fn write_to(&self, stats: &mut EncounterDamageStats, duration: i64) {
+        stats.max_stagger = self.max_stagger as i32;
+        stats.stagger_start = self.stagger_start.unwrap_or_default();
+        stats.stagger_stats = self.stats(duration);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stagger(current: u32) -> Stagger {
+        Stagger { current, max: 1000 }
+    }
+
+    #[test]
+    fn should_detect_breaks_and_compute_stats() {
+        let mut tracker = StaggerTracker::new();
+        // first break fills the bar in 10s, second one in 5s
+        for (current, timestamp) in [(0, 0), (250, 1000), (600, 6000), (1000, 11000), (0, 12000), (500, 20000), (1000, 25000)] {
+            tracker.on_stagger(&stagger(current), timestamp);
+        }
+
+        let stats = tracker.stats(120_000).unwrap();
+
+        assert_eq!(
+            tracker.breaks(),
+            [
+                StaggerBreak { timestamp: 11000, duration: 10000 },
+                StaggerBreak { timestamp: 25000, duration: 5000 },
+            ]
+        );
+        assert!((stats.average - 2.0 / 15.0 * 100.0).abs() < 1e-9);
+        assert_eq!(stats.staggers_per_min, 1.0);
+        assert_eq!(stats.log[..4], [(0, 0.0), (1, 25.0), (6, 60.0), (11, 100.0)]);
+    }
+
+    #[test]
+    fn should_discard_reset_attempts_and_repeated_full_bars() {
+        let mut tracker = StaggerTracker::new();
+        for (current, timestamp) in [(300, 1000), (0, 2000), (500, 3000), (1000, 5000), (1000, 5500)] {
+            tracker.on_stagger(&stagger(current), timestamp);
+        }
+
+        assert_eq!(tracker.breaks(), [StaggerBreak { timestamp: 5000, duration: 2000 }]);
+        assert_eq!(tracker.stats(60_000).unwrap().log.last(), Some(&(5, 100.0)));
+    }
+
+    #[test]
+    fn should_write_to_encounter_stats() {
+        let mut tracker = StaggerTracker::new();
+        let mut stats = EncounterDamageStats::default();
+        tracker.write_to(&mut stats, 60_000);
+        assert!(stats.stagger_stats.is_none());
+
+        tracker.on_stagger(&stagger(400), 1000);
+        tracker.write_to(&mut stats, 60_000);
+
+        assert_eq!(stats.max_stagger, 1000);
+        assert_eq!(stats.stagger_start, 1000);
+        assert_eq!(stats.stagger_stats.unwrap().average, 0.0);
+    }
+}
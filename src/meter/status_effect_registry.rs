@@ -0,0 +1,293 @@
+use chrono::{DateTime, Utc};
+use hashbrown::HashMap;
+
+use crate::models::{StatusEffectDetails, StatusEffectTargetType};
+
+/// Party effects are keyed by character id, local ones by entity id.
+type TargetKey = (u64, StatusEffectTargetType);
+
+/// Tracks the status effects currently applied to each target.
+#[derive(Debug, Default, Clone)]
+pub struct StatusEffectRegistry {
+    effects: HashMap<TargetKey, HashMap<u32, StatusEffectDetails>>,
+}
+
+impl StatusEffectRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies a new effect and returns the effects it replaced, either the same instance
+    /// or other instances of its unique group.
+    pub fn add(&mut self, mut effect: StatusEffectDetails) -> Vec<StatusEffectDetails> {
+        if effect.expire_at.is_none() {
+            effect.expire_at = effect.expected_expire_at();
+        }
+
+        let target = self
+            .effects
+            .entry((effect.target_id, effect.target_type))
+            .or_default();
+
+        let mut replaced = Vec::new();
+
+        if effect.unique_group != 0 {
+            let same_group: Vec<u32> = target
+                .values()
+                .filter(|existing| {
+                    existing.unique_group == effect.unique_group
+                        && existing.instance_id != effect.instance_id
+                })
+                .map(|existing| existing.instance_id)
+                .collect();
+
+            replaced.extend(same_group.iter().filter_map(|instance_id| target.remove(instance_id)));
+        }
+
+        replaced.extend(target.insert(effect.instance_id, effect));
+        replaced
+    }
+
+    pub fn remove(
+        &mut self,
+        target_id: u64,
+        target_type: StatusEffectTargetType,
+        instance_id: u32,
+    ) -> Option<StatusEffectDetails> {
+        let target = self.effects.get_mut(&(target_id, target_type))?;
+        let removed = target.remove(&instance_id);
+
+        if target.is_empty() {
+            self.effects.remove(&(target_id, target_type));
+        }
+
+        removed
+    }
+
+    /// Restarts the duration of an existing effect. Returns `false` if it is not tracked.
+    pub fn refresh(
+        &mut self,
+        target_id: u64,
+        target_type: StatusEffectTargetType,
+        instance_id: u32,
+        timestamp: DateTime<Utc>,
+        expiration_delay: f32,
+        end_tick: u64,
+    ) -> bool {
+        let Some(effect) = self.get_mut(target_id, target_type, instance_id) else {
+            return false;
+        };
+
+        effect.timestamp = timestamp;
+        effect.expiration_delay = expiration_delay;
+        effect.end_tick = end_tick;
+        effect.expire_at = effect.expected_expire_at();
+        true
+    }
+
+    pub fn update_stack_count(
+        &mut self,
+        target_id: u64,
+        target_type: StatusEffectTargetType,
+        instance_id: u32,
+        stack_count: u8,
+    ) -> bool {
+        let Some(effect) = self.get_mut(target_id, target_type, instance_id) else {
+            return false;
+        };
+
+        effect.stack_count = stack_count;
+        true
+    }
+
+    /// Updates the value of an effect (e.g. the remaining amount of a shield), returning the previous one.
+    pub fn update_value(
+        &mut self,
+        target_id: u64,
+        target_type: StatusEffectTargetType,
+        instance_id: u32,
+        value: u64,
+    ) -> Option<u64> {
+        let effect = self.get_mut(target_id, target_type, instance_id)?;
+        Some(std::mem::replace(&mut effect.value, value))
+    }
+
+    pub fn get(
+        &self,
+        target_id: u64,
+        target_type: StatusEffectTargetType,
+        instance_id: u32,
+    ) -> Option<&StatusEffectDetails> {
+        self.effects.get(&(target_id, target_type))?.get(&instance_id)
+    }
+
+    fn get_mut(
+        &mut self,
+        target_id: u64,
+        target_type: StatusEffectTargetType,
+        instance_id: u32,
+    ) -> Option<&mut StatusEffectDetails> {
+        self.effects.get_mut(&(target_id, target_type))?.get_mut(&instance_id)
+    }
+
+    /// Effects on a target which are active at `at`, ordered by instance id.
+    pub fn effects_on(
+        &self,
+        target_id: u64,
+        target_type: StatusEffectTargetType,
+        at: DateTime<Utc>,
+    ) -> Vec<StatusEffectDetails> {
+        let mut effects: Vec<StatusEffectDetails> = self
+            .effects
+            .get(&(target_id, target_type))
+            .into_iter()
+            .flat_map(|target| target.values())
+            .filter(|effect| effect.timestamp <= at && !effect.is_expired(at, None))
+            .cloned()
+            .collect();
+
+        effects.sort_by_key(|effect| effect.instance_id);
+        effects
+    }
+
+    /// Ids of the effects on a target at `at`, as used in `DamageEvent::se_on_source_ids`/`se_on_target_ids`.
+    pub fn effect_ids_on(
+        &self,
+        target_id: u64,
+        target_type: StatusEffectTargetType,
+        at: DateTime<Utc>,
+    ) -> Vec<u32> {
+        self.effects_on(target_id, target_type, at)
+            .iter()
+            .map(|effect| effect.status_effect_id)
+            .collect()
+    }
+
+    /// Drops every effect expired at `now` or `tick` and returns them.
+    pub fn expire(&mut self, now: DateTime<Utc>, tick: Option<u64>) -> Vec<StatusEffectDetails> {
+        let mut expired = Vec::new();
+
+        for target in self.effects.values_mut() {
+            let instance_ids: Vec<u32> = target
+                .values()
+                .filter(|effect| effect.is_expired(now, tick))
+                .map(|effect| effect.instance_id)
+                .collect();
+
+            expired.extend(instance_ids.iter().filter_map(|instance_id| target.remove(instance_id)));
+        }
+
+        self.effects.retain(|_, target| !target.is_empty());
+        expired
+    }
+
+    pub fn clear_target(&mut self, target_id: u64, target_type: StatusEffectTargetType) {
+        self.effects.remove(&(target_id, target_type));
+    }
+
+    pub fn clear(&mut self) {
+        self.effects.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.effects.values().map(HashMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+
+    const TARGET: u64 = 100;
+
+    fn start() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+    }
+
+    fn effect(instance_id: u32, status_effect_id: u32, unique_group: u32, seconds: f32) -> StatusEffectDetails {
+        StatusEffectDetails {
+            instance_id,
+            status_effect_id,
+            unique_group,
+            target_id: TARGET,
+            target_type: StatusEffectTargetType::Party,
+            expiration_delay: seconds,
+            timestamp: start(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn should_expire_by_wall_clock() {
+        let mut registry = StatusEffectRegistry::new();
+        registry.add(effect(1, 211601, 0, 5.0));
+        registry.add(effect(2, 211602, 0, 0.0));
+
+        let before = registry.effect_ids_on(TARGET, StatusEffectTargetType::Party, start() + TimeDelta::seconds(4));
+        let after = registry.effect_ids_on(TARGET, StatusEffectTargetType::Party, start() + TimeDelta::seconds(6));
+        let expired = registry.expire(start() + TimeDelta::seconds(6), None);
+
+        assert_eq!(before, [211601, 211602]);
+        assert_eq!(after, [211602]);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(registry.len(), 1);
+    }
+
+    #[test]
+    fn should_expire_by_tick() {
+        let mut registry = StatusEffectRegistry::new();
+        let mut ticking = effect(1, 211601, 0, 60.0);
+        ticking.end_tick = 500;
+        registry.add(ticking);
+
+        assert!(registry.expire(start(), Some(499)).is_empty());
+        assert_eq!(registry.expire(start(), Some(500)).len(), 1);
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn should_replace_effects_in_same_unique_group() {
+        let mut registry = StatusEffectRegistry::new();
+        registry.add(effect(1, 211601, 211600, 10.0));
+        registry.add(effect(2, 999, 0, 10.0));
+
+        let replaced = registry.add(effect(3, 211602, 211600, 10.0));
+
+        assert_eq!(replaced.len(), 1);
+        assert_eq!(replaced[0].instance_id, 1);
+        assert_eq!(registry.effect_ids_on(TARGET, StatusEffectTargetType::Party, start()), [999, 211602]);
+    }
+
+    #[test]
+    fn should_refresh_and_stack() {
+        let mut registry = StatusEffectRegistry::new();
+        registry.add(effect(1, 211601, 0, 5.0));
+
+        let refreshed_at = start() + TimeDelta::seconds(4);
+        assert!(registry.refresh(TARGET, StatusEffectTargetType::Party, 1, refreshed_at, 5.0, 0));
+        assert!(registry.update_stack_count(TARGET, StatusEffectTargetType::Party, 1, 3));
+        assert!(!registry.update_stack_count(TARGET, StatusEffectTargetType::Local, 1, 3));
+
+        let active = registry.effects_on(TARGET, StatusEffectTargetType::Party, start() + TimeDelta::seconds(8));
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].stack_count, 3);
+    }
+
+    #[test]
+    fn should_remove_and_update_value() {
+        let mut registry = StatusEffectRegistry::new();
+        let mut shield = effect(1, 362200, 0, 10.0);
+        shield.value = 5000;
+        registry.add(shield);
+
+        assert_eq!(registry.update_value(TARGET, StatusEffectTargetType::Party, 1, 2000), Some(5000));
+        assert_eq!(registry.remove(TARGET, StatusEffectTargetType::Party, 1).unwrap().value, 2000);
+        assert!(registry.is_empty());
+    }
+}
@@ -1,8 +1,10 @@
-use std::mem::transmute;
+use std::fmt::{self, Display, Formatter};
 
-use strum_macros::{AsRefStr, EnumString};
+use serde::{Deserialize, Serialize};
+use strum_macros::{AsRefStr, EnumIter, EnumString};
 
-#[derive(Default, Debug, Copy, Clone, AsRefStr, PartialEq, EnumString)]
+#[derive(Default, Debug, Copy, Clone, AsRefStr, PartialEq, EnumString, EnumIter, Serialize, Deserialize)]
+#[serde(into = "u32", try_from = "u32")]
 #[repr(u32)]
 pub enum Class {
     #[default]
@@ -99,8 +101,101 @@ impl Class {
     }
 }
 
-impl From<u32> for Class {
-    fn from(value: u32) -> Self {
-        unsafe { transmute(value) }
+/// Returned when a class id is not known to this version of the crate,
+/// e.g. a class added by a newer game patch. Carries the raw id.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UnknownClass(pub u32);
+
+impl Display for UnknownClass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown class id {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownClass {}
+
+impl From<Class> for u32 {
+    fn from(class: Class) -> Self {
+        class as u32
+    }
+}
+
+impl TryFrom<u32> for Class {
+    type Error = UnknownClass;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Class::Unknown),
+            101 => Ok(Class::WarriorMale),
+            102 => Ok(Class::Berserker),
+            103 => Ok(Class::Destroyer),
+            104 => Ok(Class::Gunlancer),
+            105 => Ok(Class::Paladin),
+            111 => Ok(Class::WarriorFemale),
+            112 => Ok(Class::Slayer),
+            201 => Ok(Class::Mage),
+            202 => Ok(Class::Arcanist),
+            203 => Ok(Class::Summoner),
+            204 => Ok(Class::Bard),
+            205 => Ok(Class::Sorceress),
+            301 => Ok(Class::MartialArtistFemale),
+            302 => Ok(Class::Wardancer),
+            303 => Ok(Class::Scrapper),
+            304 => Ok(Class::Soulfist),
+            305 => Ok(Class::Glaivier),
+            311 => Ok(Class::MartialArtistMale),
+            312 => Ok(Class::Striker),
+            313 => Ok(Class::Breaker),
+            401 => Ok(Class::Assassin),
+            402 => Ok(Class::Deathblade),
+            403 => Ok(Class::Shadowhunter),
+            404 => Ok(Class::Reaper),
+            405 => Ok(Class::Souleater),
+            501 => Ok(Class::GunnerMale),
+            502 => Ok(Class::Sharpshooter),
+            503 => Ok(Class::Deadeye),
+            504 => Ok(Class::Artillerist),
+            505 => Ok(Class::Machinist),
+            511 => Ok(Class::GunnerFemale),
+            512 => Ok(Class::Gunslinger),
+            601 => Ok(Class::Specialist),
+            602 => Ok(Class::Artist),
+            603 => Ok(Class::Aeromancer),
+            604 => Ok(Class::Wildsoul),
+            _ => Err(UnknownClass(value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn should_round_trip_known_classes() {
+        for class in Class::iter() {
+            assert_eq!(Class::try_from(class as u32), Ok(class));
+        }
+    }
+
+    #[test]
+    fn should_fallback_to_unknown() {
+        assert_eq!(Class::try_from(605), Err(UnknownClass(605)));
+        assert_eq!(Class::try_from(605).unwrap_or_default(), Class::Unknown);
+    }
+
+    proptest! {
+        #[test]
+        fn should_never_panic_on_arbitrary_id(value: u32) {
+            match Class::try_from(value) {
+                Ok(class) => prop_assert_eq!(class as u32, value),
+                Err(err) => {
+                    prop_assert_eq!(err, UnknownClass(value));
+                    prop_assert!(Class::iter().all(|class| class as u32 != value));
+                }
+            }
+        }
     }
 }
@@ -4,8 +4,8 @@ use serde_with::serde_as;
 
 use super::{stats::{BossHpLog, EncounterDamageStats, StaggerStats}, EncounterEntity};
 
-#[derive(Debug, Serialize, Clone, Default)]
-#[serde(rename_all = "camelCase")]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
 pub struct Encounter {
     pub last_combat_packet: i64,
     pub fight_start: i64,
@@ -48,3 +48,89 @@ pub struct EncounterMisc {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub manual_save: Option<bool>,
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    const ENCOUNTER_JSON: &str = include_str!("../../tests/fixtures/encounter.json");
+    const ENCOUNTER_NULLS_JSON: &str = include_str!("../../tests/fixtures/encounter_nulls.json");
+
+    #[test]
+    fn should_round_trip_encounter_without_loss() {
+        let expected: Value = serde_json::from_str(ENCOUNTER_JSON).unwrap();
+
+        let encounter: Encounter = serde_json::from_str(ENCOUNTER_JSON).unwrap();
+        // compare through text so f32 fields are formatted as they are on disk
+        let actual: Value = serde_json::from_str(&serde_json::to_string(&encounter).unwrap()).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn should_deserialize_nested_encounter_state() {
+        let encounter: Encounter = serde_json::from_str(ENCOUNTER_JSON).unwrap();
+        let stats = &encounter.encounter_damage_stats;
+
+        assert_eq!(encounter.local_player, "Alice");
+        assert_eq!(encounter.entities["Alice"].skills[&16140].skill_cast_log[0].hits.len(), 2);
+        assert_eq!(stats.most_damage_taken_entity.name, "Alice");
+        assert_eq!(stats.buffs[&211601].source.skill.as_ref().unwrap().class_id, 204);
+        assert_eq!(stats.misc.as_ref().unwrap().party_info.as_ref().unwrap()[&0], ["Alice"]);
+        assert_eq!(encounter.current_boss.unwrap().max_hp, 100_000_000);
+    }
+
+    #[test]
+    fn should_round_trip_null_fields() {
+        let mut expected: Value = serde_json::from_str(ENCOUNTER_NULLS_JSON).unwrap();
+        // optional fields which are skipped instead of written as null
+        let stats = expected["encounterDamageStats"].as_object_mut().unwrap();
+        stats.remove("misc");
+        stats.remove("staggerStats");
+        expected["entities"]["Alice"]["skillStats"].as_object_mut().unwrap().remove("identityStats");
+
+        let encounter: Encounter = serde_json::from_str(ENCOUNTER_NULLS_JSON).unwrap();
+        let actual: Value = serde_json::from_str(&serde_json::to_string(&encounter).unwrap()).unwrap();
+
+        assert!(encounter.current_boss.is_none());
+        assert!(encounter.difficulty.is_none());
+        assert!(encounter.encounter_damage_stats.misc.is_none());
+        assert!(encounter.entities["Alice"].engraving_data.is_none());
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn should_default_skipped_and_missing_fields() {
+        let encounter: Encounter = serde_json::from_str(r#"{
+            "fightStart": 1000,
+            "encounterDamageStats": { "totalDamageDealt": 5 }
+        }"#).unwrap();
+        let stats = &encounter.encounter_damage_stats;
+
+        assert_eq!(encounter.fight_start, 1000);
+        assert_eq!(stats.total_damage_dealt, 5);
+        assert!(stats.unknown_buffs.is_empty());
+        assert_eq!(stats.max_stagger, 0);
+        assert_eq!(stats.stagger_start, 0);
+        assert!(stats.misc.is_none());
+    }
+
+    #[test]
+    fn should_not_persist_live_meter_state() {
+        let mut encounter: Encounter = serde_json::from_str(ENCOUNTER_JSON).unwrap();
+        let stats = &mut encounter.encounter_damage_stats;
+        stats.unknown_buffs.insert(999);
+        stats.max_stagger = 1000;
+        stats.stagger_start = 5000;
+
+        let json: Value = serde_json::from_str(&serde_json::to_string(&encounter).unwrap()).unwrap();
+        let decoded: Encounter = serde_json::from_value(json.clone()).unwrap();
+
+        let stats = json["encounterDamageStats"].as_object().unwrap();
+        assert!(["unknownBuffs", "maxStagger", "staggerStart"].iter().all(|key| !stats.contains_key(*key)));
+        assert!(decoded.encounter_damage_stats.unknown_buffs.is_empty());
+        assert_eq!(decoded.encounter_damage_stats.max_stagger, 0);
+    }
+}
@@ -6,6 +6,8 @@ use serde_with::DefaultOnError;
 use std::fmt::{self, Display, Formatter};
 
 use crate::models::IncapacitationEventType;
+use crate::models::StatusEffectDetails;
+use crate::models::StatusEffectType;
 
 use super::entity::EntityType;
 use super::player::ArkPassiveData;
@@ -135,7 +137,7 @@ impl EncounterEntity {
             .take_while(|x| x.timestamp + x.duration > self.damage_stats.death_time)
             .for_each(|x| {
                 // cap duration to death time if it exceeds it
-                x.duration = x.timestamp - self.damage_stats.death_time;
+                x.duration = (self.damage_stats.death_time - x.timestamp).max(0);
             });
     }
 
@@ -182,6 +184,44 @@ impl EncounterEntity {
         );
     }
 
+    /// Records a crowd control event for a hard CC status effect (stun, freeze, fear, ...).
+    /// Other status effects are ignored.
+    pub fn on_crowd_control(&mut self, status_effect: &StatusEffectDetails, timestamp: i64) {
+        if status_effect.status_effect_type != StatusEffectType::HardCrowdControl
+            || status_effect.is_infinite()
+        {
+            return;
+        }
+
+        let duration = (status_effect.expiration_delay * 1000.0) as i64;
+        self.damage_stats.incapacitations.push(IncapacitatedEvent {
+            event_type: IncapacitationEventType::CrowdControl,
+            timestamp,
+            duration,
+        });
+
+        info!(
+            "Player {} will be crowd controlled by {} for {}ms",
+            self.name, status_effect.status_effect_id, duration
+        );
+    }
+
+    /// Ends the crowd control event which started at `started_at` early, e.g. when its status
+    /// effect is removed. Other overlapping crowd control events keep their duration.
+    pub fn shorten_crowd_control(&mut self, started_at: i64, timestamp: i64) {
+        let ongoing_event = self
+            .damage_stats
+            .incapacitations
+            .iter_mut()
+            .rev()
+            .filter(|x| x.event_type == IncapacitationEventType::CrowdControl)
+            .find(|x| x.timestamp == started_at && x.timestamp + x.duration > timestamp);
+
+        if let Some(ongoing_event) = ongoing_event {
+            ongoing_event.duration = (timestamp - ongoing_event.timestamp).max(0);
+        }
+    }
+
     pub fn shorten_incapacitation(&mut self, timestamp: i64) {
         let events = self
             .damage_stats
@@ -6,7 +6,8 @@ use std::fmt::{self, Display, Formatter};
 
 use super::Class;
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
 pub struct Entity {
     pub id: u64,
     pub is_local_player: bool,
@@ -88,20 +89,17 @@ pub enum NpcGrade {
 
 impl Npc {
     pub fn is_boss(&self) -> bool {
-        match self.grade {
-            NpcGrade::Boss 
+        matches!(self.grade, NpcGrade::Boss
             | NpcGrade::Commander
             | NpcGrade::Raid
-            | NpcGrade::EpicRaid => true,
-            _ => false
-        }
+            | NpcGrade::EpicRaid)
     }
 
     pub fn has_valid_name(&self) -> bool {
 
         if let Some(name) = &self.name {
             let contains_underscore = name.contains('_');
-            let all_ascii = name.chars().all(|c| c.is_ascii());
+            let all_ascii = name.is_ascii();
 
             return !contains_underscore && all_ascii;
         }
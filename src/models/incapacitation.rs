@@ -0,0 +1,233 @@
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use super::{Encounter, EncounterEntity, IncapacitatedEvent, IncapacitationEventType};
+
+/// Time spent incapacitated, in milliseconds.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IncapacitationSummary {
+    pub fall_down: i64,
+    pub crowd_control: i64,
+    /// Union of all events, so overlapping knock downs and crowd control count once.
+    pub total: i64,
+}
+
+impl IncapacitationSummary {
+    /// Summarises the events which overlap the `[start, end)` window, clipped to it.
+    pub fn from_events(events: &[IncapacitatedEvent], start: i64, end: i64) -> Self {
+        let clipped = |event_type: Option<&IncapacitationEventType>| {
+            let intervals = events
+                .iter()
+                .filter(|event| event_type.is_none_or(|event_type| event.event_type == *event_type))
+                .filter_map(|event| clip(event, start, end));
+            merge_intervals(intervals)
+                .iter()
+                .map(|(from, to)| to - from)
+                .sum()
+        };
+
+        Self {
+            fall_down: clipped(Some(&IncapacitationEventType::FallDown)),
+            crowd_control: clipped(Some(&IncapacitationEventType::CrowdControl)),
+            total: clipped(None),
+        }
+    }
+}
+
+impl std::ops::AddAssign for IncapacitationSummary {
+    fn add_assign(&mut self, other: Self) {
+        self.fall_down += other.fall_down;
+        self.crowd_control += other.crowd_control;
+        self.total += other.total;
+    }
+}
+
+fn clip(event: &IncapacitatedEvent, start: i64, end: i64) -> Option<(i64, i64)> {
+    let from = event.timestamp.max(start);
+    let to = (event.timestamp + event.duration.max(0)).min(end);
+    (from < to).then_some((from, to))
+}
+
+/// Merges `[from, to)` intervals into a sorted list of disjoint intervals.
+pub fn merge_intervals(intervals: impl IntoIterator<Item = (i64, i64)>) -> Vec<(i64, i64)> {
+    let mut intervals: Vec<(i64, i64)> = intervals.into_iter().filter(|(from, to)| from < to).collect();
+    intervals.sort_unstable();
+
+    let mut merged: Vec<(i64, i64)> = Vec::with_capacity(intervals.len());
+    for (from, to) in intervals {
+        match merged.last_mut() {
+            Some(last) if from <= last.1 => last.1 = last.1.max(to),
+            _ => merged.push((from, to)),
+        }
+    }
+
+    merged
+}
+
+impl EncounterEntity {
+    pub fn incapacitation_summary(&self) -> IncapacitationSummary {
+        IncapacitationSummary::from_events(&self.damage_stats.incapacitations, i64::MIN, i64::MAX)
+    }
+
+    /// Time incapacitated within `[start, end)`, e.g. during a boss mechanic.
+    pub fn incapacitation_summary_between(&self, start: i64, end: i64) -> IncapacitationSummary {
+        IncapacitationSummary::from_events(&self.damage_stats.incapacitations, start, end)
+    }
+
+    /// Merged intervals during which the entity could not act.
+    pub fn incapacitated_intervals(&self) -> Vec<(i64, i64)> {
+        merge_intervals(
+            self.damage_stats
+                .incapacitations
+                .iter()
+                .map(|event| (event.timestamp, event.timestamp + event.duration)),
+        )
+    }
+}
+
+impl Encounter {
+    /// Per player summaries for valid players.
+    pub fn incapacitation_summaries(&self) -> HashMap<String, IncapacitationSummary> {
+        self.entities
+            .values()
+            .filter(|entity| entity.is_valid_player())
+            .map(|entity| (entity.name.clone(), entity.incapacitation_summary()))
+            .collect()
+    }
+
+    /// Sum of the player summaries, optionally limited to `[start, end)`.
+    pub fn raid_incapacitation_summary(&self, window: Option<(i64, i64)>) -> IncapacitationSummary {
+        let (start, end) = window.unwrap_or((i64::MIN, i64::MAX));
+
+        self.entities
+            .values()
+            .filter(|entity| entity.is_valid_player())
+            .map(|entity| entity.incapacitation_summary_between(start, end))
+            .fold(IncapacitationSummary::default(), |mut total, summary| {
+                total += summary;
+                total
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::*;
+
+    use super::*;
+
+    fn event(event_type: IncapacitationEventType, timestamp: i64, duration: i64) -> IncapacitatedEvent {
+        IncapacitatedEvent {
+            event_type,
+            timestamp,
+            duration,
+        }
+    }
+
+    fn player(name: &str, incapacitations: Vec<IncapacitatedEvent>) -> EncounterEntity {
+        EncounterEntity {
+            name: name.to_string(),
+            entity_type: EntityType::Player,
+            character_id: 1,
+            class_id: Class::Berserker as u32,
+            damage_stats: DamageStats {
+                incapacitations,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn should_merge_overlapping_intervals() {
+        let merged = merge_intervals([(5, 8), (0, 2), (1, 4), (8, 9), (10, 10)]);
+
+        assert_eq!(merged, [(0, 4), (5, 9)]);
+    }
+
+    #[test]
+    fn should_split_fall_down_and_crowd_control() {
+        let entity = player(
+            "Alice",
+            vec![
+                event(IncapacitationEventType::FallDown, 1000, 2000),
+                event(IncapacitationEventType::CrowdControl, 2000, 2000),
+                event(IncapacitationEventType::FallDown, 10_000, 500),
+            ],
+        );
+
+        let summary = entity.incapacitation_summary();
+        let window = entity.incapacitation_summary_between(2500, 10_250);
+
+        assert_eq!(summary, IncapacitationSummary { fall_down: 2500, crowd_control: 2000, total: 3500 });
+        assert_eq!(window, IncapacitationSummary { fall_down: 750, crowd_control: 1500, total: 1750 });
+        assert_eq!(entity.incapacitated_intervals(), [(1000, 4000), (10_000, 10_500)]);
+    }
+
+    #[test]
+    fn should_aggregate_raid() {
+        let mut encounter = Encounter::default();
+        for entity in [
+            player("Alice", vec![event(IncapacitationEventType::FallDown, 0, 1000)]),
+            player("Bob", vec![event(IncapacitationEventType::CrowdControl, 500, 1000)]),
+        ] {
+            encounter.entities.insert(entity.name.clone(), entity);
+        }
+
+        let summaries = encounter.incapacitation_summaries();
+
+        assert_eq!(summaries["Bob"].crowd_control, 1000);
+        assert_eq!(encounter.raid_incapacitation_summary(None).total, 2000);
+        assert_eq!(encounter.raid_incapacitation_summary(Some((0, 1000))).total, 1500);
+    }
+
+    #[test]
+    fn should_record_hard_crowd_control() {
+        let mut entity = player("Alice", vec![]);
+        let stun = StatusEffectDetails {
+            status_effect_type: StatusEffectType::HardCrowdControl,
+            expiration_delay: 3.0,
+            ..Default::default()
+        };
+        let shield = StatusEffectDetails {
+            status_effect_type: StatusEffectType::Shield,
+            expiration_delay: 3.0,
+            ..Default::default()
+        };
+
+        entity.on_crowd_control(&stun, 1000);
+        entity.on_crowd_control(&shield, 1000);
+        entity.shorten_crowd_control(1000, 2500);
+
+        assert_eq!(entity.damage_stats.incapacitations.len(), 1);
+        assert_eq!(entity.incapacitation_summary().crowd_control, 1500);
+    }
+
+    #[test]
+    fn should_only_shorten_removed_crowd_control() {
+        let mut entity = player("Alice", vec![]);
+        let stun = |seconds| StatusEffectDetails {
+            status_effect_type: StatusEffectType::HardCrowdControl,
+            expiration_delay: seconds,
+            ..Default::default()
+        };
+
+        entity.on_crowd_control(&stun(2.0), 1000);
+        entity.on_crowd_control(&stun(5.0), 1500);
+        entity.shorten_crowd_control(1000, 2000);
+
+        let durations: Vec<i64> = entity.damage_stats.incapacitations.iter().map(|event| event.duration).collect();
+        assert_eq!(durations, [1000, 5000]);
+    }
+
+    #[test]
+    fn should_cap_durations_to_death_time() {
+        let mut entity = player("Alice", vec![event(IncapacitationEventType::FallDown, 1000, 5000)]);
+        entity.damage_stats.death_time = 3000;
+
+        entity.cap_incapacitation_durations_to_death_time();
+
+        assert_eq!(entity.damage_stats.incapacitations[0].duration, 2000);
+    }
+}
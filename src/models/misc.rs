@@ -1,7 +1,8 @@
-use std::mem::transmute;
+use std::fmt::{self, Display, Formatter};
 
 use hashbrown::HashMap;
 use serde::{Deserialize, Serialize};
+use strum_macros::EnumIter;
 use uuid::Uuid;
 
 use super::{Entity, StatusEffect, StatusEffectDetails};
@@ -33,11 +34,12 @@ pub struct DamageEvent<'a> {
     pub se_on_target_ids: Vec<u32>,
 }
 
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub struct DamageResult {
     pub is_raid_start: bool,
 }
 
-#[derive(Debug, Serialize, Clone, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Identity {
     pub gauge1: u32,
@@ -45,7 +47,7 @@ pub struct Identity {
     pub gauge3: u32,
 }
 
-#[derive(Debug, Serialize, Clone, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Stagger {
     pub current: u32,
@@ -85,9 +87,10 @@ pub struct LocalPlayer {
     pub count: i32,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, EnumIter, Serialize, Deserialize)]
 #[repr(i32)]
 pub enum HitOption {
+    #[default]
     None = 0,
     BackAttack = 1,
     FrontalAttack = 2,
@@ -95,15 +98,37 @@ pub enum HitOption {
     Max = 4,
 }
 
-impl From<i32> for HitOption {
-    fn from(value: i32) -> Self {
-        unsafe { transmute(value) }
+/// Returned when a hit option is not known to this version of the crate. Carries the raw value.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UnknownHitOption(pub i32);
+
+impl Display for UnknownHitOption {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown hit option {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownHitOption {}
+
+impl TryFrom<i32> for HitOption {
+    type Error = UnknownHitOption;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(HitOption::None),
+            1 => Ok(HitOption::BackAttack),
+            2 => Ok(HitOption::FrontalAttack),
+            3 => Ok(HitOption::FlankAttack),
+            4 => Ok(HitOption::Max),
+            _ => Err(UnknownHitOption(value)),
+        }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, EnumIter, Serialize, Deserialize)]
 #[repr(u32)]
 pub enum HitFlag {
+    #[default]
     Normal = 0,
     Critical = 1,
     Miss = 2,
@@ -120,9 +145,39 @@ pub enum HitFlag {
     Max = 13,
 }
 
-impl From<i32> for HitFlag {
-    fn from(value: i32) -> Self {
-        unsafe { transmute(value) }
+/// Returned when a hit flag is not known to this version of the crate. Carries the raw value.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UnknownHitFlag(pub i32);
+
+impl Display for UnknownHitFlag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown hit flag {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownHitFlag {}
+
+impl TryFrom<i32> for HitFlag {
+    type Error = UnknownHitFlag;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(HitFlag::Normal),
+            1 => Ok(HitFlag::Critical),
+            2 => Ok(HitFlag::Miss),
+            3 => Ok(HitFlag::Invincible),
+            4 => Ok(HitFlag::DamageOverTime),
+            5 => Ok(HitFlag::Immune),
+            6 => Ok(HitFlag::ImmuneSilenced),
+            7 => Ok(HitFlag::FontSilence),
+            8 => Ok(HitFlag::DamageOverTimeCritical),
+            9 => Ok(HitFlag::Dodge),
+            10 => Ok(HitFlag::Reflect),
+            11 => Ok(HitFlag::DamageShare),
+            12 => Ok(HitFlag::DodgeHit),
+            13 => Ok(HitFlag::Max),
+            _ => Err(UnknownHitFlag(value)),
+        }
     }
 }
 
@@ -146,3 +201,48 @@ pub struct PassiveOption {
     pub key_index: i32,
     pub value: i32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn should_round_trip_known_hit_flags() {
+        for flag in HitFlag::iter() {
+            assert_eq!(HitFlag::try_from(flag as i32), Ok(flag));
+        }
+    }
+
+    #[test]
+    fn should_round_trip_known_hit_options() {
+        for option in HitOption::iter() {
+            assert_eq!(HitOption::try_from(option as i32), Ok(option));
+        }
+    }
+
+    #[test]
+    fn should_reject_unknown_hit_flag() {
+        assert_eq!(HitFlag::try_from(14), Err(UnknownHitFlag(14)));
+        assert_eq!(HitFlag::try_from(-1), Err(UnknownHitFlag(-1)));
+    }
+
+    proptest! {
+        #[test]
+        fn should_never_panic_on_arbitrary_hit_flag(value: i32) {
+            match HitFlag::try_from(value) {
+                Ok(flag) => prop_assert_eq!(flag as i32, value),
+                Err(err) => prop_assert_eq!(err, UnknownHitFlag(value)),
+            }
+        }
+
+        #[test]
+        fn should_never_panic_on_arbitrary_hit_option(value: i32) {
+            match HitOption::try_from(value) {
+                Ok(option) => prop_assert_eq!(option as i32, value),
+                Err(err) => prop_assert_eq!(err, UnknownHitOption(value)),
+            }
+        }
+    }
+}
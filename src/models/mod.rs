@@ -1,5 +1,6 @@
 mod entity;
 mod settings;
+mod settings_validation;
 mod skill;
 mod status_effect;
 mod misc;
@@ -14,11 +15,13 @@ mod stats_api;
 mod class;
 mod class_skills;
 mod engraving;
+mod incapacitation;
 
-pub use class::Class;
+pub use class::*;
 pub use class_skills::*;
 pub use entity::*;
 pub use settings::*;
+pub use settings_validation::*;
 pub use skill::*;
 pub use status_effect::*;
 pub use misc::*;
@@ -30,4 +33,5 @@ pub use summary::*;
 pub use combat_effect::*;
 pub use encounter_entity::*;
 pub use stats_api::*;
-pub use engraving::*;
\ No newline at end of file
+pub use engraving::*;
+pub use incapacitation::*;
@@ -12,7 +12,7 @@ pub struct Settings {
     pub sync: SyncSettings,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", default)]
 pub struct GeneralSettings {
     pub start_loa_on_start: bool,
@@ -65,7 +65,49 @@ pub struct GeneralSettings {
     pub logs_per_page: i32,
 }
 
-
+/// Matches the serde defaults, so a default value equals a deserialized empty object.
+impl Default for GeneralSettings {
+    fn default() -> Self {
+        Self {
+            start_loa_on_start: false,
+            low_performance_mode: false,
+            show_names: default_true(),
+            show_gear_score: false,
+            hide_names: false,
+            show_esther: default_true(),
+            show_date: default_true(),
+            show_difficulty: default_true(),
+            show_gate: false,
+            split_lines: default_true(),
+            underline_hovered: false,
+            show_details: false,
+            show_shields: false,
+            show_tanked: false,
+            show_bosses: false,
+            hide_logo: false,
+            accent_color: String::new(),
+            raw_socket: false,
+            auto_iface: default_true(),
+            if_desc: String::new(),
+            ip: String::new(),
+            port: 0,
+            blur: false,
+            blur_win11: false,
+            transparent: false,
+            scale: default_scale(),
+            log_scale: default_scale(),
+            always_on_top: default_true(),
+            boss_only_damage: default_true(),
+            keep_favorites: default_true(),
+            hide_meter_on_start: false,
+            hide_logs_on_start: false,
+            constant_local_player_color: false,
+            boss_only_damage_default_on: default_true(),
+            start_on_boot: false,
+            logs_per_page: 0,
+        }
+    }
+}
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", default)]
@@ -0,0 +1,290 @@
+use std::fmt::{self, Display, Formatter};
+use std::net::IpAddr;
+
+use super::{Settings, Shortcut};
+
+/// Accepted values of `general.scale` and `general.logScale`.
+pub const SCALES: [&str; 4] = ["0", "1", "2", "3"];
+
+pub const SHORTCUT_MODIFIERS: [&str; 4] = ["", "Ctrl", "Alt", "Shift"];
+
+/// Accepted values of `sync.visibility`: public, unlisted and private.
+pub const SYNC_VISIBILITIES: [&str; 4] = ["", "0", "1", "2"];
+
+pub const MAX_LOGS_PER_PAGE: i32 = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueSeverity {
+    /// The value is used as is but is probably not what was intended.
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsIssueKind {
+    OutOfRange,
+    InvalidValue,
+    /// Key which no setting reads, usually a typo.
+    UnknownKey,
+    /// Value which clashes with another setting.
+    Conflict,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettingsIssue {
+    /// camelCase path as in `settings.json`, e.g. `general.logsPerPage`.
+    pub path: String,
+    pub severity: IssueSeverity,
+    pub kind: SettingsIssueKind,
+    pub message: String,
+}
+
+impl Display for SettingsIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SettingsReport {
+    pub issues: Vec<SettingsIssue>,
+}
+
+impl SettingsReport {
+    pub fn is_valid(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &SettingsIssue> {
+        self.issues.iter().filter(|issue| issue.severity == IssueSeverity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &SettingsIssue> {
+        self.issues.iter().filter(|issue| issue.severity == IssueSeverity::Warning)
+    }
+
+    pub fn issue(&self, path: &str) -> Option<&SettingsIssue> {
+        self.issues.iter().find(|issue| issue.path == path)
+    }
+
+    pub fn error(&mut self, path: impl Into<String>, kind: SettingsIssueKind, message: impl Into<String>) {
+        self.push(path.into(), IssueSeverity::Error, kind, message.into());
+    }
+
+    pub fn warning(&mut self, path: impl Into<String>, kind: SettingsIssueKind, message: impl Into<String>) {
+        self.push(path.into(), IssueSeverity::Warning, kind, message.into());
+    }
+
+    fn push(&mut self, path: String, severity: IssueSeverity, kind: SettingsIssueKind, message: String) {
+        self.issues.push(SettingsIssue {
+            path,
+            severity,
+            kind,
+            message,
+        });
+    }
+}
+
+fn is_valid_accent_color(color: &str) -> bool {
+    if let Some(hex) = color.strip_prefix('#') {
+        return matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit());
+    }
+
+    color
+        .strip_prefix("theme-")
+        .is_some_and(|name| !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase()))
+}
+
+impl Settings {
+    /// Checks values which deserialize fine but cannot be used, see [`SettingsReport`].
+    /// Unknown keys are only detected on the raw file, see [`crate::schema::settings_from_json`].
+    pub fn validate(&self) -> SettingsReport {
+        let mut report = SettingsReport::default();
+        let general = &self.general;
+
+        match general.logs_per_page {
+            ..0 => report.error("general.logsPerPage", SettingsIssueKind::OutOfRange, "must be positive"),
+            0 => report.warning("general.logsPerPage", SettingsIssueKind::OutOfRange, "not set, the default page size is used"),
+            MAX_LOGS_PER_PAGE.. => report.warning(
+                "general.logsPerPage",
+                SettingsIssueKind::OutOfRange,
+                format!("more than {} logs per page slows down the log browser", MAX_LOGS_PER_PAGE),
+            ),
+            _ => {}
+        }
+
+        for (path, scale) in [("general.scale", &general.scale), ("general.logScale", &general.log_scale)] {
+            if !SCALES.contains(&scale.as_str()) {
+                report.error(
+                    path,
+                    SettingsIssueKind::InvalidValue,
+                    format!("invalid scale {:?}, expected one of {}", scale, SCALES.join(", ")),
+                );
+            }
+        }
+
+        if !general.accent_color.is_empty() && !is_valid_accent_color(&general.accent_color) {
+            report.error(
+                "general.accentColor",
+                SettingsIssueKind::InvalidValue,
+                format!("invalid color {:?}, expected #rgb, #rrggbb or theme-<name>", general.accent_color),
+            );
+        }
+
+        if general.port == 0 {
+            if general.auto_iface {
+                report.warning("general.port", SettingsIssueKind::OutOfRange, "port 0 is not a valid port");
+            } else {
+                report.error("general.port", SettingsIssueKind::OutOfRange, "a port is required when the interface is set manually");
+            }
+        }
+
+        if !general.auto_iface && general.ip.parse::<IpAddr>().is_err() {
+            report.error(
+                "general.ip",
+                SettingsIssueKind::InvalidValue,
+                format!("invalid ip address {:?}", general.ip),
+            );
+        }
+
+        if general.hide_names && general.show_names {
+            report.warning("general.showNames", SettingsIssueKind::Conflict, "names are hidden by general.hideNames");
+        }
+
+        if self.logs.min_encounter_duration < 0 {
+            report.error("logs.minEncounterDuration", SettingsIssueKind::OutOfRange, "must not be negative");
+        }
+
+        self.validate_shortcuts(&mut report);
+
+        if !SYNC_VISIBILITIES.contains(&self.sync.visibility.as_str()) {
+            report.error(
+                "sync.visibility",
+                SettingsIssueKind::InvalidValue,
+                format!("invalid visibility {:?}, expected 0, 1 or 2", self.sync.visibility),
+            );
+        }
+
+        if self.sync.enabled && self.sync.access_token.is_empty() {
+            report.warning("sync.accessToken", SettingsIssueKind::Conflict, "sync is enabled without an access token");
+        }
+
+        report
+    }
+
+    fn validate_shortcuts(&self, report: &mut SettingsReport) {
+        let shortcuts = &self.shortcuts;
+        let shortcuts: [(&str, &Shortcut); 7] = [
+            ("hideMeter", &shortcuts.hide_meter),
+            ("showLogs", &shortcuts.show_logs),
+            ("showLatestEncounter", &shortcuts.show_latest_encounter),
+            ("resetSession", &shortcuts.reset_session),
+            ("pauseSession", &shortcuts.pause_session),
+            ("manualSave", &shortcuts.manual_save),
+            ("disableClickthrough", &shortcuts.disable_clickthrough),
+        ];
+
+        for (index, (name, shortcut)) in shortcuts.iter().enumerate() {
+            let path = format!("shortcuts.{}", name);
+
+            if !SHORTCUT_MODIFIERS.contains(&shortcut.modifier.as_str()) {
+                report.error(
+                    format!("{}.modifier", path),
+                    SettingsIssueKind::InvalidValue,
+                    format!("invalid modifier {:?}, expected Ctrl, Alt or Shift", shortcut.modifier),
+                );
+            }
+
+            if shortcut.key.is_empty() {
+                if !shortcut.modifier.is_empty() {
+                    report.warning(format!("{}.key", path), SettingsIssueKind::InvalidValue, "modifier without a key");
+                }
+                continue;
+            }
+
+            if let Some((other, _)) = shortcuts[..index].iter().find(|(_, other)| *other == *shortcut) {
+                report.warning(
+                    path,
+                    SettingsIssueKind::Conflict,
+                    format!("same key binding as shortcuts.{}", other),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn valid_settings() -> Settings {
+        serde_json::from_str(r#"{ "general": { "logsPerPage": 10, "port": 6040 } }"#).unwrap()
+    }
+
+    #[test]
+    fn should_accept_default_file() {
+        let report = valid_settings().validate();
+
+        assert_eq!(report.issues, []);
+        assert!(report.is_valid());
+    }
+
+    #[test]
+    fn should_accept_default_settings() {
+        let report = Settings::default().validate();
+
+        assert!(report.is_valid(), "{:?}", report.errors().collect::<Vec<_>>());
+        assert_eq!(Settings::default(), serde_json::from_str::<Settings>("{}").unwrap());
+    }
+
+    #[test]
+    fn should_report_invalid_general_values() {
+        let mut settings = valid_settings();
+        settings.general.logs_per_page = -5;
+        settings.general.log_scale = "huge".to_string();
+        settings.general.accent_color = "#12345".to_string();
+        settings.general.auto_iface = false;
+        settings.general.ip = "192.168.1.300".to_string();
+        settings.general.port = 0;
+
+        let report = settings.validate();
+
+        let paths: Vec<&str> = report.errors().map(|issue| issue.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            ["general.logsPerPage", "general.logScale", "general.accentColor", "general.port", "general.ip"]
+        );
+        assert_eq!(report.issue("general.logScale").unwrap().kind, SettingsIssueKind::InvalidValue);
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn should_accept_theme_and_hex_colors() {
+        assert!(is_valid_accent_color("#fff"));
+        assert!(is_valid_accent_color("#A1b2C3"));
+        assert!(is_valid_accent_color("theme-pink"));
+        assert!(!is_valid_accent_color("pink"));
+        assert!(!is_valid_accent_color("#ggg"));
+        assert!(!is_valid_accent_color("theme-"));
+    }
+
+    #[test]
+    fn should_warn_about_conflicting_shortcuts() {
+        let mut settings = valid_settings();
+        let binding = Shortcut {
+            modifier: "Ctrl".to_string(),
+            key: "ArrowDown".to_string(),
+        };
+        settings.shortcuts.hide_meter = binding.clone();
+        settings.shortcuts.reset_session = binding;
+        settings.shortcuts.manual_save.modifier = "Meta".to_string();
+
+        let report = settings.validate();
+
+        let issue = report.issue("shortcuts.resetSession").unwrap();
+        assert_eq!(issue.severity, IssueSeverity::Warning);
+        assert_eq!(issue.to_string(), "shortcuts.resetSession: same key binding as shortcuts.hideMeter");
+        assert_eq!(report.errors().count(), 1);
+        assert_eq!(report.issue("shortcuts.manualSave.modifier").unwrap().kind, SettingsIssueKind::InvalidValue);
+    }
+}
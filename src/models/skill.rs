@@ -83,8 +83,12 @@ pub struct Skill {
     pub rdps_damage_received_support: i64,
     pub rdps_damage_given: i64,
     pub skill_cast_log: Vec<SkillCast>,
+    /// Time of the last hit in milliseconds since the fight started.
     #[serde(skip)]
     pub last_timestamp: i64,
+    /// Damage dealt by the latest entry of `skill_cast_log` so far.
+    #[serde(skip)]
+    pub last_cast_damage: i64,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
@@ -6,11 +6,12 @@ use serde_with::DefaultOnError;
 use super::entity::EntityType;
 use super::player::ArkPassiveData;
 use super::skill::Skill;
+use super::Class;
 use super::Entity;
 use super::{encounter::EncounterMisc, misc::IncapacitatedEvent, status_effect::StatusEffect};
 
-#[derive(Debug, Serialize, Clone, Default)]
-#[serde(rename_all = "camelCase")]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
 pub struct EncounterDamageStats {
     pub total_damage_dealt: i64,
     pub top_damage_dealt: i64,
@@ -23,8 +24,11 @@ pub struct EncounterDamageStats {
     pub total_shielding: u64,
     pub total_effective_shielding: u64,
     pub applied_shield_buffs: HashMap<u32, StatusEffect>,
+    /// Buff ids missing from the game data, so each one is only warned about once.
+    /// This and the stagger fields below are live meter state and are not written to disk.
     #[serde(skip)]
     pub unknown_buffs: HashSet<u32>,
+    /// Stagger gauge of the current boss, summarized in `stagger_stats` once the encounter ends.
     #[serde(skip)]
     pub max_stagger: i32,
     #[serde(skip)]
@@ -45,11 +49,12 @@ pub struct SkillStats {
     pub back_attacks: i64,
     pub front_attacks: i64,
     pub counters: i64,
+    /// JSON of [`IdentityStats`], see its docs for the layout.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub identity_stats: Option<String>,
 }
-#[derive(Debug, Serialize, Clone, Default)]
-#[serde(rename_all = "camelCase")]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
 pub struct MostDamageTakenEntity {
     pub name: String,
     pub damage_taken: i64,
@@ -120,6 +125,37 @@ pub struct IdentityGeneric {
     pub average: f64,
 }
 
+/// Typed form of `SkillStats::identity_stats`.
+///
+/// The string stored in `identity_stats` is the camelCase JSON of the inner struct, without a tag:
+/// - Arcanist: `{"log": [[seconds, [percent, card1, card2]], ...], "average": f64, "cardDraws": {"card": count}}`
+/// - Bard and Artist: `{"log": [[seconds, [percent, bubbles]], ...], "average": f64}`
+/// - every other class: `{"log": [[seconds, percent], ...], "average": f64}`
+///
+/// `seconds` are relative to the fight start and `average` is the time-weighted gauge percentage.
+#[derive(Debug, Serialize, Clone)]
+#[serde(untagged)]
+pub enum IdentityStats {
+    Arcanist(IdentityArcanist),
+    ArtistBard(IdentityArtistBard),
+    Generic(IdentityGeneric),
+}
+
+impl IdentityStats {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("identity stats are always serializable")
+    }
+
+    /// Parses an `identity_stats` string; the layout depends on the class it was recorded for.
+    pub fn from_json(class: Class, json: &str) -> serde_json::Result<Self> {
+        match class {
+            Class::Arcanist => serde_json::from_str(json).map(IdentityStats::Arcanist),
+            Class::Bard | Class::Artist => serde_json::from_str(json).map(IdentityStats::ArtistBard),
+            _ => serde_json::from_str(json).map(IdentityStats::Generic),
+        }
+    }
+}
+
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct StaggerStats {
@@ -48,7 +48,7 @@ pub struct GemData {
 
 #[derive(Debug, Default, Clone, Deserialize)]
 #[serde(rename_all = "camelCase", default)]
-pub struct Engraving {
+pub struct EngravingLevel {
     pub id: u32,
     pub level: u8,
 }
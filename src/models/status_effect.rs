@@ -1,4 +1,4 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeDelta, Utc};
 use serde::{Deserialize, Serialize};
 use bitflags::bitflags;
 use super::skill::SkillData;
@@ -50,21 +50,21 @@ bitflags! {
     }
 }
 
-#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum StatusEffectTargetType {
     #[default]
     Party = 0,
     Local = 1,
 }
 
-#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum StatusEffectCategory {
     #[default]
     Other = 0,
     Debuff = 1,
 }
 
-#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum StatusEffectBuffCategory {
     #[default]
     Other = 0,
@@ -86,14 +86,14 @@ impl From<&str> for StatusEffectBuffCategory {
     }
 }
 
-#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum StatusEffectShowType {
     #[default]
     Other = 0,
     All = 1,
 }
 
-#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum StatusEffectType {
     #[default]
     Shield = 0,
@@ -114,7 +114,8 @@ impl From<&str> for StatusEffectType {
     }
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
 pub struct StatusEffectDetails {
     pub instance_id: u32,
     pub status_effect_id: u32,
@@ -132,6 +133,8 @@ pub struct StatusEffectDetails {
     pub buff_category: StatusEffectBuffCategory,
     pub show_type: StatusEffectShowType,
     pub status_effect_type: StatusEffectType,
+    /// Effects sharing a non-zero unique group replace each other on the same target.
+    pub unique_group: u32,
     pub expiration_delay: f32,
     pub expire_at: Option<DateTime<Utc>>,
     pub end_tick: u64,
@@ -154,4 +157,26 @@ impl StatusEffectDetails {
         // infinite if duration is (sub-)zero or longer than an hour
         self.expiration_delay <= 0.0 || self.expiration_delay > 3600.0
     }
+
+    /// Wall-clock expiry derived from `timestamp` and `expiration_delay`, `None` when infinite.
+    pub fn expected_expire_at(&self) -> Option<DateTime<Utc>> {
+        if self.is_infinite() {
+            return None;
+        }
+
+        let delay = TimeDelta::milliseconds((self.expiration_delay * 1000.0) as i64);
+        Some(self.timestamp + delay)
+    }
+
+    /// An effect is expired once `now` passes `expire_at` or, when both are known, `tick` reaches `end_tick`.
+    pub fn is_expired(&self, now: DateTime<Utc>, tick: Option<u64>) -> bool {
+        if self.is_infinite() {
+            return false;
+        }
+
+        let expired_by_time = self.expire_at.is_some_and(|expire_at| expire_at <= now);
+        let expired_by_tick = self.end_tick != 0 && tick.is_some_and(|tick| tick >= self.end_tick);
+
+        expired_by_time || expired_by_tick
+    }
 }
\ No newline at end of file
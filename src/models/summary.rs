@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use super::{Class, Encounter, EncounterEntity};
+
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct EncounterPreview {
@@ -14,6 +16,68 @@ pub struct EncounterPreview {
     pub my_dps: i64,
     pub favorite: bool,
     pub cleared: bool,
+    #[serde(default)]
+    pub boss_only_damage: bool,
+}
+
+impl EncounterPreview {
+    /// Builds the list-view summary of an encounter.
+    ///
+    /// Players are taken from [`EncounterEntity::is_valid_player`] and ordered by damage dealt, highest first.
+    /// Class ids unknown to [`Class`] are listed as [`Class::Unknown`].
+    pub fn from_encounter(encounter: &Encounter, id: i32) -> Self {
+        let duration = if encounter.duration > 0 {
+            encounter.duration
+        } else {
+            (encounter.last_combat_packet - encounter.fight_start).max(0)
+        };
+
+        let mut players: Vec<&EncounterEntity> = encounter
+            .entities
+            .values()
+            .filter(|entity| entity.is_valid_player())
+            .collect();
+        players.sort_by(|a, b| {
+            b.damage_stats
+                .damage_dealt
+                .cmp(&a.damage_stats.damage_dealt)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        let my_dps = encounter
+            .entities
+            .get(&encounter.local_player)
+            .map(|entity| entity_dps(entity, duration))
+            .unwrap_or_default();
+
+        Self {
+            id,
+            fight_start: encounter.fight_start,
+            boss_name: encounter.current_boss_name.clone(),
+            duration,
+            classes: players
+                .iter()
+                .map(|entity| Class::try_from(entity.class_id).unwrap_or_default() as i32)
+                .collect(),
+            names: players.iter().map(|entity| entity.name.clone()).collect(),
+            difficulty: encounter.difficulty.clone(),
+            local_player: encounter.local_player.clone(),
+            my_dps,
+            favorite: encounter.favorite,
+            cleared: encounter.cleared,
+            boss_only_damage: encounter.boss_only_damage,
+        }
+    }
+}
+
+/// Uses the stored DPS and derives it from damage dealt when it was never computed.
+fn entity_dps(entity: &EncounterEntity, duration: i64) -> i64 {
+    if entity.damage_stats.dps != 0 {
+        return entity.damage_stats.dps;
+    }
+
+    let seconds = (duration / 1000).max(1);
+    entity.damage_stats.damage_dealt / seconds
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
@@ -44,3 +108,88 @@ pub struct EncounterDbInfo {
     pub total_encounters: i32,
     pub total_encounters_filtered: i32,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::models::*;
+
+    use super::*;
+
+    fn player(name: &str, class: Class, damage_dealt: i64) -> EncounterEntity {
+        EncounterEntity {
+            name: name.to_string(),
+            entity_type: EntityType::Player,
+            character_id: 1,
+            class_id: class as u32,
+            class: class.as_ref().to_string(),
+            damage_stats: DamageStats {
+                damage_dealt,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn should_build_preview_from_encounter() {
+        let mut unknown_class = player("Charlie", Class::Bard, 8000);
+        unknown_class.class_id = 0;
+        let entities = [
+            player("Alice", Class::Berserker, 1000),
+            player("Bob", Class::Sorceress, 3000),
+            unknown_class,
+            player("You", Class::Paladin, 5000),
+            EncounterEntity {
+                name: "Thaemine".to_string(),
+                entity_type: EntityType::Boss,
+                damage_stats: DamageStats {
+                    damage_dealt: 9999,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        ];
+        let encounter = Encounter {
+            fight_start: 1000,
+            last_combat_packet: 11_000,
+            local_player: "Alice".to_string(),
+            current_boss_name: "Thaemine".to_string(),
+            difficulty: Some("Hard".to_string()),
+            cleared: true,
+            entities: entities
+                .into_iter()
+                .map(|entity| (entity.name.clone(), entity))
+                .collect(),
+            ..Default::default()
+        };
+
+        let preview = EncounterPreview::from_encounter(&encounter, 7);
+
+        assert_eq!(preview.id, 7);
+        assert_eq!(preview.duration, 10_000);
+        assert_eq!(preview.boss_name, "Thaemine");
+        assert_eq!(preview.names, ["Bob", "Alice"]);
+        assert_eq!(
+            preview.classes,
+            [Class::Sorceress as i32, Class::Berserker as i32]
+        );
+        assert_eq!(preview.my_dps, 100);
+        assert!(preview.cleared);
+        assert_eq!(preview.difficulty.as_deref(), Some("Hard"));
+    }
+
+    #[test]
+    fn should_list_unknown_class_ids_as_unknown() {
+        let mut newer_class = player("Alice", Class::Berserker, 1000);
+        newer_class.class_id = 605;
+        let encounter = Encounter {
+            entities: [(newer_class.name.clone(), newer_class)].into_iter().collect(),
+            ..Default::default()
+        };
+
+        let preview = EncounterPreview::from_encounter(&encounter, 1);
+
+        assert_eq!(preview.names, ["Alice"]);
+        assert_eq!(preview.classes, [Class::Unknown as i32]);
+    }
+}
@@ -0,0 +1,40 @@
+//! Commonly used types, re-exported for a single glob import.
+//!
+//! ```
+//! use lost_metrics_core::prelude::*;
+//!
+//! let encounter = Encounter::default();
+//! assert!(encounter.entities.is_empty());
+//! ```
+
+pub use crate::models::{
+    Class,
+    DamageEvent,
+    DamageResult,
+    DamageStats,
+    Encounter,
+    EncounterDamageStats,
+    EncounterEntity,
+    EncounterMisc,
+    EncounterPreview,
+    EncountersOverview,
+    Entity,
+    EntityType,
+    HitFlag,
+    HitOption,
+    IncapacitatedEvent,
+    IncapacitationEventType,
+    Npc,
+    NpcGrade,
+    SearchFilter,
+    Settings,
+    Skill,
+    SkillCast,
+    SkillData,
+    SkillHit,
+    StatusEffect,
+    StatusEffectDetails,
+};
+
+pub use crate::game_data::GameData;
+pub use crate::meter::{DamageRecord, EncounterBuilder, EncounterState, GameDataProvider, MeterDriver, MeterEvent};
@@ -0,0 +1,155 @@
+use hashbrown::HashMap;
+use serde_json::{Map, Value};
+
+use crate::models::Class;
+
+use super::{schema_version, SchemaError, ENCOUNTER_SCHEMA_VERSION, SCHEMA_VERSION_KEY};
+
+type Migration = fn(&mut Map<String, Value>) -> Result<(), SchemaError>;
+
+/// `MIGRATIONS[n]` upgrades a version `n + 1` encounter to version `n + 2`.
+const MIGRATIONS: [Migration; (ENCOUNTER_SCHEMA_VERSION - 1) as usize] = [
+    move_misc_logs_to_damage_stats,
+    fill_hp_percent_and_class_names,
+];
+
+/// Upgrades a serialized encounter in place to [`ENCOUNTER_SCHEMA_VERSION`].
+/// Returns the version the encounter had before migrating.
+pub fn migrate_encounter(value: &mut Value) -> Result<u32, SchemaError> {
+    let version = schema_version(value)?;
+
+    if version == 0 || version > ENCOUNTER_SCHEMA_VERSION {
+        return Err(SchemaError::UnsupportedVersion(version));
+    }
+
+    let encounter = value
+        .as_object_mut()
+        .ok_or_else(|| SchemaError::InvalidLayout("encounter is not an object".to_string()))?;
+
+    for migration in &MIGRATIONS[(version - 1) as usize..] {
+        migration(encounter)?;
+    }
+
+    encounter.insert(SCHEMA_VERSION_KEY.to_string(), ENCOUNTER_SCHEMA_VERSION.into());
+
+    Ok(version)
+}
+
+/// v1 -> v2: `bossHpLog` and `staggerStats` used to be stored only in `encounterDamageStats.misc`.
+///
+/// They are copied next to `misc` and kept in it, since [`EncounterMisc`](crate::models::EncounterMisc)
+/// still reads them from there.
+fn move_misc_logs_to_damage_stats(encounter: &mut Map<String, Value>) -> Result<(), SchemaError> {
+    let Some(Value::Object(stats)) = encounter.get_mut("encounterDamageStats") else {
+        return Ok(());
+    };
+
+    let Some(Value::Object(misc)) = stats.get("misc") else {
+        return Ok(());
+    };
+
+    let legacy: Vec<(&str, Value)> = ["bossHpLog", "staggerStats"]
+        .into_iter()
+        .filter_map(|key| Some((key, misc.get(key)?.clone())))
+        .collect();
+
+    for (key, legacy) in legacy {
+        let is_missing = stats.get(key).is_none_or(|current| match current {
+            Value::Null => true,
+            Value::Object(map) => map.is_empty(),
+            _ => false,
+        });
+
+        if is_missing {
+            stats.insert(key.to_string(), legacy);
+        }
+    }
+
+    Ok(())
+}
+
+/// v2 -> v3: boss HP samples gained a `p` ratio and entities a `class` name.
+fn fill_hp_percent_and_class_names(encounter: &mut Map<String, Value>) -> Result<(), SchemaError> {
+    if let Some(Value::Object(entities)) = encounter.get_mut("entities") {
+        entities.values_mut().for_each(fill_class_name);
+    }
+
+    if let Some(boss) = encounter.get_mut("currentBoss") {
+        fill_class_name(boss);
+    }
+
+    let max_hp_by_name: HashMap<String, i64> = encounter
+        .get("entities")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, entity)| {
+            let max_hp = entity.get("maxHp").and_then(Value::as_i64).filter(|max_hp| *max_hp > 0)?;
+            Some((name.clone(), max_hp))
+        })
+        .collect();
+
+    let Some(Value::Object(stats)) = encounter.get_mut("encounterDamageStats") else {
+        return Ok(());
+    };
+
+    if let Some(boss_hp_log) = stats.get_mut("bossHpLog") {
+        fill_hp_percent(boss_hp_log, &max_hp_by_name)?;
+    }
+
+    if let Some(boss_hp_log) = stats.get_mut("misc").and_then(|misc| misc.get_mut("bossHpLog")) {
+        fill_hp_percent(boss_hp_log, &max_hp_by_name)?;
+    }
+
+    Ok(())
+}
+
+fn fill_hp_percent(boss_hp_log: &mut Value, max_hp_by_name: &HashMap<String, i64>) -> Result<(), SchemaError> {
+    let Value::Object(boss_hp_log) = boss_hp_log else {
+        return Ok(());
+    };
+
+    for (name, samples) in boss_hp_log {
+        let Value::Array(samples) = samples else {
+            return Err(SchemaError::InvalidLayout(format!("boss hp log of {} is not an array", name)));
+        };
+
+        // without a known max hp the first sample is the best estimate of a full bar
+        let max_hp = max_hp_by_name
+            .get(name)
+            .copied()
+            .or_else(|| samples.first().and_then(|sample| sample.get("hp")).and_then(Value::as_i64))
+            .filter(|max_hp| *max_hp > 0);
+
+        for sample in samples.iter_mut().filter_map(Value::as_object_mut) {
+            if sample.contains_key("p") {
+                continue;
+            }
+
+            let hp = sample.get("hp").and_then(Value::as_i64).unwrap_or_default();
+            let percent = max_hp.map_or(0.0, |max_hp| hp as f64 / max_hp as f64);
+            sample.insert("p".to_string(), percent.into());
+        }
+    }
+
+    Ok(())
+}
+
+fn fill_class_name(entity: &mut Value) {
+    let Some(entity) = entity.as_object_mut() else {
+        return;
+    };
+
+    if entity.get("class").is_some_and(Value::is_string) {
+        return;
+    }
+
+    let class = entity
+        .get("classId")
+        .and_then(Value::as_u64)
+        .and_then(|class_id| u32::try_from(class_id).ok())
+        .and_then(|class_id| Class::try_from(class_id).ok())
+        .unwrap_or_default();
+
+    entity.insert("class".to_string(), class.as_ref().into());
+}
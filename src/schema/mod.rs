@@ -0,0 +1,217 @@
+mod migrations;
+mod settings;
+
+use std::fmt::{self, Display, Formatter};
+
+use serde_json::Value;
+
+use crate::models::Encounter;
+
+pub use migrations::*;
+pub use settings::*;
+
+/// Version written to the `schemaVersion` field of serialized encounters.
+///
+/// | Version | Layout |
+/// |---------|--------|
+/// | 1 | Unversioned files, as written by loa-logs. Boss HP log and stagger stats live under `encounterDamageStats.misc`. |
+/// | 2 | Boss HP log and stagger stats are also stored in `encounterDamageStats`; HP samples have no `p`. |
+/// | 3 | HP samples carry `p`, entities always carry their `class` name. |
+///
+/// Quirks older than versioning are absorbed by the models rather than migrated: non-list
+/// `dpsAverage` values default to empty, buff `type` may be a number, and Esther data may list
+/// `npcs` instead of `npc_ids`.
+pub const ENCOUNTER_SCHEMA_VERSION: u32 = 3;
+
+pub const SCHEMA_VERSION_KEY: &str = "schemaVersion";
+
+#[derive(Debug)]
+pub enum SchemaError {
+    /// The file was written by a newer version of the crate.
+    UnsupportedVersion(u32),
+    /// The settings file was written by a newer version of the crate.
+    UnsupportedSettingsVersion(u32),
+    InvalidLayout(String),
+    InvalidSettings(String),
+    Json(serde_json::Error),
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::UnsupportedVersion(version) => write!(
+                f,
+                "encounter schema version {} is newer than supported version {}",
+                version, ENCOUNTER_SCHEMA_VERSION
+            ),
+            SchemaError::UnsupportedSettingsVersion(version) => write!(
+                f,
+                "settings version {} is newer than supported version {}",
+                version, SETTINGS_VERSION
+            ),
+            SchemaError::InvalidLayout(reason) => write!(f, "invalid encounter layout: {}", reason),
+            SchemaError::InvalidSettings(reason) => write!(f, "invalid settings: {}", reason),
+            SchemaError::Json(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl From<serde_json::Error> for SchemaError {
+    fn from(err: serde_json::Error) -> Self {
+        SchemaError::Json(err)
+    }
+}
+
+/// Serializes an encounter, tagging it with [`ENCOUNTER_SCHEMA_VERSION`].
+pub fn encounter_to_json(encounter: &Encounter) -> Result<Value, SchemaError> {
+    let mut value = serde_json::to_value(encounter)?;
+    value
+        .as_object_mut()
+        .ok_or_else(|| SchemaError::InvalidLayout("encounter is not an object".to_string()))?
+        .insert(SCHEMA_VERSION_KEY.to_string(), ENCOUNTER_SCHEMA_VERSION.into());
+    Ok(value)
+}
+
+/// Serializes an encounter to text with sorted object keys, so equal encounters give identical bytes.
+///
+/// `serde_json::to_string` on the encounter itself writes maps in hash order, which differs between runs.
+pub fn encounter_to_canonical_string(encounter: &Encounter) -> Result<String, SchemaError> {
+    Ok(serde_json::to_string(&encounter_to_json(encounter)?)?)
+}
+
+/// Upgrades `value` to the current layout and deserializes it.
+pub fn encounter_from_json(mut value: Value) -> Result<Encounter, SchemaError> {
+    migrate_encounter(&mut value)?;
+    Ok(serde_json::from_value(value)?)
+}
+
+pub fn encounter_from_str(json: &str) -> Result<Encounter, SchemaError> {
+    encounter_from_json(serde_json::from_str(json)?)
+}
+
+/// Reads the schema version of a serialized encounter, treating unversioned files as version 1.
+pub fn schema_version(value: &Value) -> Result<u32, SchemaError> {
+    match value.get(SCHEMA_VERSION_KEY) {
+        None | Some(Value::Null) => Ok(1),
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| SchemaError::InvalidLayout(format!("invalid schema version {}", version))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hashbrown::HashMap;
+    use serde_json::json;
+
+    use crate::models::{Esther, SkillBuffData};
+
+    use super::*;
+
+    const CURRENT_JSON: &str = include_str!("../../tests/fixtures/encounter.json");
+    const V1_JSON: &str = include_str!("../../tests/fixtures/encounter_v1.json");
+    const V2_JSON: &str = include_str!("../../tests/fixtures/encounter_v2.json");
+    const V1_BAD_DPS_AVERAGE_JSON: &str = include_str!("../../tests/fixtures/encounter_v1_bad_dps_average.json");
+    const ESTHER_NPCS_JSON: &str = include_str!("../../tests/fixtures/esther_npcs.json");
+    const SKILL_BUFF_TYPE_JSON: &str = include_str!("../../tests/fixtures/skill_buff_type.json");
+
+    fn to_canonical(encounter: &Encounter) -> Value {
+        serde_json::from_str(&serde_json::to_string(encounter).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn should_migrate_v1_layout() {
+        let expected = to_canonical(&serde_json::from_str(CURRENT_JSON).unwrap());
+
+        let encounter = encounter_from_str(V1_JSON).unwrap();
+
+        // the legacy copies stay in `misc`, which still models them
+        let mut actual = to_canonical(&encounter);
+        let misc = actual["encounterDamageStats"]["misc"].as_object_mut().unwrap();
+        let boss_hp_log = misc.remove("bossHpLog").unwrap();
+        let stagger_stats = misc.remove("staggerStats").unwrap();
+
+        assert_eq!(actual, expected);
+        assert_eq!(boss_hp_log, expected["encounterDamageStats"]["bossHpLog"]);
+        assert_eq!(stagger_stats, expected["encounterDamageStats"]["staggerStats"]);
+    }
+
+    #[test]
+    fn should_default_malformed_dps_averages() {
+        let expected = encounter_from_str(V1_JSON).unwrap();
+
+        let encounter = encounter_from_str(V1_BAD_DPS_AVERAGE_JSON).unwrap();
+
+        let damage_stats = &encounter.entities["Alice"].damage_stats;
+        assert!(damage_stats.dps_average.is_empty());
+        assert!(damage_stats.dps_rolling_10s_avg.is_empty());
+        assert!(encounter.current_boss.unwrap().damage_stats.dps_average.is_empty());
+        assert_eq!(damage_stats.damage_dealt, expected.entities["Alice"].damage_stats.damage_dealt);
+    }
+
+    #[test]
+    fn should_read_legacy_esther_npc_ids() {
+        let esthers: Vec<Esther> = serde_json::from_str(ESTHER_NPCS_JSON).unwrap();
+
+        assert_eq!(esthers[0].npc_ids, [725000, 725001]);
+        assert_eq!(esthers[1].npc_ids, [725010]);
+    }
+
+    #[test]
+    fn should_read_numeric_and_named_buff_types() {
+        let buffs: HashMap<u32, SkillBuffData> = serde_json::from_str(SKILL_BUFF_TYPE_JSON).unwrap();
+
+        assert_eq!(buffs[&211601].buff_type, "skill_damage_amplify");
+        assert_eq!(buffs[&210230].buff_type, "1");
+    }
+
+    #[test]
+    fn should_migrate_v2_layout() {
+        let expected = to_canonical(&serde_json::from_str(CURRENT_JSON).unwrap());
+
+        let encounter = encounter_from_str(V2_JSON).unwrap();
+
+        assert_eq!(to_canonical(&encounter), expected);
+        assert_eq!(encounter.entities["Alice"].class, "Berserker");
+    }
+
+    #[test]
+    fn should_round_trip_current_version() {
+        let encounter: Encounter = serde_json::from_str(CURRENT_JSON).unwrap();
+
+        let value = encounter_to_json(&encounter).unwrap();
+        assert_eq!(value[SCHEMA_VERSION_KEY], ENCOUNTER_SCHEMA_VERSION);
+
+        let decoded = encounter_from_json(value).unwrap();
+        assert_eq!(to_canonical(&decoded), to_canonical(&encounter));
+    }
+
+    #[test]
+    fn should_only_apply_migrations_after_stored_version() {
+        let mut value = json!({
+            "schemaVersion": 2,
+            "encounterDamageStats": {
+                "misc": { "bossHpLog": { "Boss": [{ "time": 0, "hp": 10 }] } },
+                "bossHpLog": {}
+            }
+        });
+
+        let version = migrate_encounter(&mut value).unwrap();
+
+        assert_eq!(version, 2);
+        assert_eq!(value[SCHEMA_VERSION_KEY], ENCOUNTER_SCHEMA_VERSION);
+        assert!(value["encounterDamageStats"]["misc"].get("bossHpLog").is_some());
+    }
+
+    #[test]
+    fn should_reject_newer_schema() {
+        let value = json!({ "schemaVersion": ENCOUNTER_SCHEMA_VERSION + 1 });
+
+        let result = encounter_from_json(value);
+
+        assert!(matches!(result, Err(SchemaError::UnsupportedVersion(_))));
+    }
+}
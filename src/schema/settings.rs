@@ -0,0 +1,216 @@
+use serde_json::{Map, Value};
+
+use crate::models::{Settings, SettingsIssueKind, SettingsReport};
+
+use super::SchemaError;
+
+/// Version written to the `settingsVersion` field of `settings.json`.
+///
+/// | Version | Layout |
+/// |---------|--------|
+/// | 1 | Unversioned files. Hand-edited files may use snake_case keys. |
+/// | 2 | All keys are camelCase. |
+pub const SETTINGS_VERSION: u32 = 2;
+
+pub const SETTINGS_VERSION_KEY: &str = "settingsVersion";
+
+type Migration = fn(&mut Map<String, Value>);
+
+/// `MIGRATIONS[n]` upgrades version `n + 1` settings to version `n + 2`.
+const MIGRATIONS: [Migration; (SETTINGS_VERSION - 1) as usize] = [rename_snake_case_keys];
+
+/// Reads the version of a settings file, treating unversioned files as version 1.
+pub fn settings_version(value: &Value) -> Result<u32, SchemaError> {
+    match value.get(SETTINGS_VERSION_KEY) {
+        None | Some(Value::Null) => Ok(1),
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| SchemaError::InvalidSettings(format!("invalid version {}", version))),
+    }
+}
+
+/// Upgrades a settings file in place to [`SETTINGS_VERSION`].
+/// Returns the version the file had before migrating.
+pub fn migrate_settings(value: &mut Value) -> Result<u32, SchemaError> {
+    let version = settings_version(value)?;
+
+    if version == 0 || version > SETTINGS_VERSION {
+        return Err(SchemaError::UnsupportedSettingsVersion(version));
+    }
+
+    let settings = value
+        .as_object_mut()
+        .ok_or_else(|| SchemaError::InvalidSettings("not an object".to_string()))?;
+
+    for migration in &MIGRATIONS[(version - 1) as usize..] {
+        migration(settings);
+    }
+
+    settings.insert(SETTINGS_VERSION_KEY.to_string(), SETTINGS_VERSION.into());
+
+    Ok(version)
+}
+
+pub fn settings_to_json(settings: &Settings) -> Result<Value, SchemaError> {
+    let mut value = serde_json::to_value(settings)?;
+    value
+        .as_object_mut()
+        .ok_or_else(|| SchemaError::InvalidSettings("not an object".to_string()))?
+        .insert(SETTINGS_VERSION_KEY.to_string(), SETTINGS_VERSION.into());
+    Ok(value)
+}
+
+/// Migrates and deserializes a settings file.
+///
+/// The report combines [`Settings::validate`] with warnings for keys no setting reads.
+pub fn settings_from_json(mut value: Value) -> Result<(Settings, SettingsReport), SchemaError> {
+    migrate_settings(&mut value)?;
+
+    let mut report = SettingsReport::default();
+    let known = serde_json::to_value(Settings::default())?;
+    if let (Value::Object(settings), Value::Object(known)) = (&value, &known) {
+        find_unknown_keys(settings, known, "", &mut report);
+    }
+
+    let settings: Settings = serde_json::from_value(value)?;
+    report.issues.extend(settings.validate().issues);
+
+    Ok((settings, report))
+}
+
+pub fn settings_from_str(json: &str) -> Result<(Settings, SettingsReport), SchemaError> {
+    settings_from_json(serde_json::from_str(json)?)
+}
+
+fn find_unknown_keys(value: &Map<String, Value>, known: &Map<String, Value>, prefix: &str, report: &mut SettingsReport) {
+    for (key, value) in value {
+        if prefix.is_empty() && key == SETTINGS_VERSION_KEY {
+            continue;
+        }
+
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+
+        match (known.get(key), value) {
+            (None, _) => report.warning(path, SettingsIssueKind::UnknownKey, "unknown setting"),
+            (Some(Value::Object(known)), Value::Object(value)) => find_unknown_keys(value, known, &path, report),
+            _ => {}
+        }
+    }
+}
+
+fn snake_to_camel_case(key: &str) -> String {
+    let mut camel = String::with_capacity(key.len());
+    let mut upper = false;
+    for c in key.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            camel.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            camel.push(c);
+        }
+    }
+    camel
+}
+
+/// v1 -> v2: snake_case keys such as `logs_per_page` become `logsPerPage`.
+/// A camelCase key which is already present wins over its snake_case spelling.
+fn rename_snake_case_keys(settings: &mut Map<String, Value>) {
+    let keys: Vec<String> = settings.keys().filter(|key| key.contains('_')).cloned().collect();
+    for key in keys {
+        let renamed = snake_to_camel_case(&key);
+        if let Some(value) = settings.remove(&key) {
+            if !settings.contains_key(&renamed) {
+                settings.insert(renamed, value);
+            }
+        }
+    }
+
+    for value in settings.values_mut() {
+        if let Value::Object(nested) = value {
+            rename_snake_case_keys(nested);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::models::IssueSeverity;
+
+    use super::*;
+
+    #[test]
+    fn should_rename_snake_case_keys_of_unversioned_files() {
+        let (settings, report) = settings_from_json(json!({
+            "general": { "logs_per_page": 25, "logsPerPage": 30, "accent_color": "theme-pink", "port": 6040 },
+            "logs": { "breakdown": { "avg_damage": true } }
+        }))
+        .unwrap();
+
+        assert_eq!(settings.general.logs_per_page, 30);
+        assert_eq!(settings.general.accent_color, "theme-pink");
+        assert!(settings.logs.breakdown.avg_damage);
+        assert!(report.is_valid());
+    }
+
+    #[test]
+    fn should_keep_snake_case_keys_of_current_files() {
+        let mut value = json!({ "settingsVersion": 2, "general": { "logs_per_page": 25 } });
+
+        assert_eq!(migrate_settings(&mut value).unwrap(), 2);
+        assert_eq!(value["general"]["logs_per_page"], 25);
+    }
+
+    #[test]
+    fn should_report_unknown_keys_by_path() {
+        let (_, report) = settings_from_json(json!({
+            "settingsVersion": 2,
+            "general": { "logsPerPage": 10, "port": 6040, "showNmaes": true },
+            "meter": { "breakdown": { "hmp": true } },
+            "theme": "dark"
+        }))
+        .unwrap();
+
+        let unknown: Vec<&str> = report
+            .issues
+            .iter()
+            .filter(|issue| issue.kind == SettingsIssueKind::UnknownKey)
+            .map(|issue| issue.path.as_str())
+            .collect();
+        assert_eq!(unknown, ["general.showNmaes", "meter.breakdown.hmp", "theme"]);
+        assert!(report.issues.iter().all(|issue| issue.severity == IssueSeverity::Warning));
+    }
+
+    #[test]
+    fn should_round_trip_settings() {
+        let (settings, _) = settings_from_json(json!({ "general": { "logsPerPage": 10 } })).unwrap();
+
+        let value = settings_to_json(&settings).unwrap();
+
+        assert_eq!(value[SETTINGS_VERSION_KEY], SETTINGS_VERSION);
+        assert_eq!(settings_from_json(value).unwrap().0, settings);
+    }
+
+    #[test]
+    fn should_reject_newer_settings() {
+        let result = settings_from_str(r#"{ "settingsVersion": 3 }"#);
+
+        assert!(matches!(result, Err(SchemaError::UnsupportedSettingsVersion(3))));
+    }
+
+    #[test]
+    fn should_reject_malformed_settings() {
+        let err = settings_from_str("[]").unwrap_err();
+
+        assert!(matches!(err, SchemaError::InvalidSettings(_)));
+        assert_eq!(err.to_string(), "invalid settings: not an object");
+    }
+}
@@ -0,0 +1,314 @@
+use std::cmp::Ordering;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use crate::models::{EncounterPreview, EncountersOverview, SearchFilter};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchError {
+    UnknownSortKey(String),
+    /// `order` must be `1` (ascending) or `2` (descending).
+    InvalidOrder(u8),
+    /// Pages are numbered from 1.
+    InvalidPage(i32),
+    InvalidPageSize(i32),
+}
+
+impl Display for SearchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SearchError::UnknownSortKey(key) => write!(f, "unknown sort key: {}", key),
+            SearchError::InvalidOrder(order) => write!(f, "invalid sort order: {}", order),
+            SearchError::InvalidPage(page) => write!(f, "invalid page: {}", page),
+            SearchError::InvalidPageSize(size) => write!(f, "invalid logs per page: {}", size),
+        }
+    }
+}
+
+impl std::error::Error for SearchError {}
+
+/// Column encounters are sorted by. An empty key sorts by id.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    #[default]
+    Id,
+    FightStart,
+    Duration,
+    MyDps,
+    BossName,
+}
+
+impl FromStr for SortKey {
+    type Err = SearchError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "" | "id" => Ok(SortKey::Id),
+            "fight_start" | "fightStart" => Ok(SortKey::FightStart),
+            "duration" => Ok(SortKey::Duration),
+            "my_dps" | "myDps" => Ok(SortKey::MyDps),
+            "boss_name" | "bossName" => Ok(SortKey::BossName),
+            _ => Err(SearchError::UnknownSortKey(value.to_string())),
+        }
+    }
+}
+
+/// Sort direction. `0` is treated as unset and sorts newest first.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Ascending,
+    #[default]
+    Descending,
+}
+
+impl TryFrom<u8> for SortOrder {
+    type Error = SearchError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(SortOrder::Ascending),
+            0 | 2 => Ok(SortOrder::Descending),
+            _ => Err(SearchError::InvalidOrder(value)),
+        }
+    }
+}
+
+/// Validated form of a [`SearchFilter`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct EncounterQuery {
+    pub bosses: Vec<String>,
+    /// Inclusive lower bound in seconds.
+    pub min_duration: i32,
+    /// Exclusive upper bound in seconds, no limit when not positive.
+    pub max_duration: i32,
+    pub cleared_only: bool,
+    pub favorites_only: bool,
+    pub difficulty: Option<String>,
+    pub boss_only_damage: bool,
+    pub sort: SortKey,
+    pub order: SortOrder,
+}
+
+impl TryFrom<&SearchFilter> for EncounterQuery {
+    type Error = SearchError;
+
+    fn try_from(filter: &SearchFilter) -> Result<Self, Self::Error> {
+        Ok(Self {
+            bosses: filter.bosses.clone(),
+            min_duration: filter.min_duration,
+            max_duration: filter.max_duration,
+            cleared_only: filter.cleared,
+            favorites_only: filter.favorite,
+            difficulty: (!filter.difficulty.is_empty()).then(|| filter.difficulty.clone()),
+            boss_only_damage: filter.boss_only_damage,
+            sort: filter.sort.parse()?,
+            order: filter.order.try_into()?,
+        })
+    }
+}
+
+impl EncounterQuery {
+    pub fn matches(&self, preview: &EncounterPreview) -> bool {
+        let seconds = preview.duration / 1000;
+
+        (self.bosses.is_empty() || self.bosses.contains(&preview.boss_name))
+            && seconds >= self.min_duration as i64
+            && (self.max_duration <= 0 || seconds < self.max_duration as i64)
+            && (!self.cleared_only || preview.cleared)
+            && (!self.favorites_only || preview.favorite)
+            && self
+                .difficulty
+                .as_ref()
+                .is_none_or(|difficulty| preview.difficulty.as_ref() == Some(difficulty))
+            && (!self.boss_only_damage || preview.boss_only_damage)
+    }
+
+    pub fn compare(&self, a: &EncounterPreview, b: &EncounterPreview) -> Ordering {
+        let ordering = match self.sort {
+            SortKey::Id => a.id.cmp(&b.id),
+            SortKey::FightStart => a.fight_start.cmp(&b.fight_start),
+            SortKey::Duration => a.duration.cmp(&b.duration),
+            SortKey::MyDps => a.my_dps.cmp(&b.my_dps),
+            SortKey::BossName => a.boss_name.cmp(&b.boss_name),
+        }
+        .then_with(|| a.id.cmp(&b.id));
+
+        match self.order {
+            SortOrder::Ascending => ordering,
+            SortOrder::Descending => ordering.reverse(),
+        }
+    }
+
+    /// Filters and sorts `previews`, returning the 1-based `page` of `logs_per_page` results.
+    /// `total_encounters` counts every match, not only the returned page.
+    pub fn search<'a>(
+        &self,
+        previews: impl IntoIterator<Item = &'a EncounterPreview>,
+        page: i32,
+        logs_per_page: i32,
+    ) -> Result<EncountersOverview, SearchError> {
+        validate_page(page, logs_per_page)?;
+
+        let mut matches: Vec<&EncounterPreview> = previews
+            .into_iter()
+            .filter(|preview| self.matches(preview))
+            .collect();
+        matches.sort_by(|a, b| self.compare(a, b));
+
+        let total_encounters = matches.len() as i32;
+        let encounters = matches
+            .into_iter()
+            .skip((page as usize - 1) * logs_per_page as usize)
+            .take(logs_per_page as usize)
+            .cloned()
+            .collect();
+
+        Ok(EncountersOverview {
+            encounters,
+            total_encounters,
+        })
+    }
+}
+
+pub fn validate_page(page: i32, logs_per_page: i32) -> Result<(), SearchError> {
+    if page < 1 {
+        return Err(SearchError::InvalidPage(page));
+    }
+
+    if logs_per_page < 1 {
+        return Err(SearchError::InvalidPageSize(logs_per_page));
+    }
+
+    Ok(())
+}
+
+/// Runs `filter` over `previews`, using [`GeneralSettings::logs_per_page`](crate::models::GeneralSettings::logs_per_page) as page size.
+pub fn search_encounters<'a>(
+    previews: impl IntoIterator<Item = &'a EncounterPreview>,
+    filter: &SearchFilter,
+    page: i32,
+    logs_per_page: i32,
+) -> Result<EncountersOverview, SearchError> {
+    EncounterQuery::try_from(filter)?.search(previews, page, logs_per_page)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preview(id: i32, boss_name: &str, duration_s: i64, my_dps: i64) -> EncounterPreview {
+        EncounterPreview {
+            id,
+            fight_start: id as i64 * 1000,
+            boss_name: boss_name.to_string(),
+            duration: duration_s * 1000,
+            my_dps,
+            ..Default::default()
+        }
+    }
+
+    fn previews() -> Vec<EncounterPreview> {
+        vec![
+            EncounterPreview {
+                cleared: true,
+                difficulty: Some("Hard".to_string()),
+                ..preview(1, "Thaemine", 600, 300)
+            },
+            EncounterPreview {
+                favorite: true,
+                boss_only_damage: true,
+                ..preview(2, "Thaemine", 120, 100)
+            },
+            EncounterPreview {
+                cleared: true,
+                difficulty: Some("Normal".to_string()),
+                ..preview(3, "Echidna", 300, 200)
+            },
+            preview(4, "Echidna", 30, 400),
+        ]
+    }
+
+    fn ids(overview: &EncountersOverview) -> Vec<i32> {
+        overview.encounters.iter().map(|preview| preview.id).collect()
+    }
+
+    #[test]
+    fn should_sort_by_id_descending_by_default() {
+        let overview = search_encounters(&previews(), &SearchFilter::default(), 1, 10).unwrap();
+
+        assert_eq!(ids(&overview), [4, 3, 2, 1]);
+        assert_eq!(overview.total_encounters, 4);
+    }
+
+    #[test]
+    fn should_apply_every_filter_field() {
+        let previews = previews();
+        let search = |filter: SearchFilter| ids(&search_encounters(&previews, &filter, 1, 10).unwrap());
+
+        let bosses = vec!["Echidna".to_string()];
+        assert_eq!(search(SearchFilter { bosses, ..Default::default() }), [4, 3]);
+        assert_eq!(search(SearchFilter { min_duration: 120, ..Default::default() }), [3, 2, 1]);
+        assert_eq!(search(SearchFilter { max_duration: 300, ..Default::default() }), [4, 2]);
+        assert_eq!(search(SearchFilter { cleared: true, ..Default::default() }), [3, 1]);
+        assert_eq!(search(SearchFilter { favorite: true, ..Default::default() }), [2]);
+        assert_eq!(search(SearchFilter { difficulty: "Hard".to_string(), ..Default::default() }), [1]);
+        assert_eq!(search(SearchFilter { boss_only_damage: true, ..Default::default() }), [2]);
+    }
+
+    #[test]
+    fn should_sort_by_key_and_order() {
+        let filter = SearchFilter {
+            sort: "my_dps".to_string(),
+            order: 1,
+            ..Default::default()
+        };
+
+        let overview = search_encounters(&previews(), &filter, 1, 10).unwrap();
+
+        assert_eq!(ids(&overview), [2, 3, 1, 4]);
+    }
+
+    #[test]
+    fn should_paginate_and_count_all_matches() {
+        let filter = SearchFilter {
+            order: 1,
+            ..Default::default()
+        };
+
+        let overview = search_encounters(&previews(), &filter, 2, 3).unwrap();
+
+        assert_eq!(ids(&overview), [4]);
+        assert_eq!(overview.total_encounters, 4);
+    }
+
+    #[test]
+    fn should_reject_invalid_queries() {
+        let filter = SearchFilter {
+            sort: "damage".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            search_encounters(&previews(), &filter, 1, 10).unwrap_err(),
+            SearchError::UnknownSortKey("damage".to_string())
+        );
+
+        let filter = SearchFilter {
+            order: 3,
+            ..Default::default()
+        };
+        assert_eq!(
+            search_encounters(&previews(), &filter, 1, 10).unwrap_err(),
+            SearchError::InvalidOrder(3)
+        );
+
+        let filter = SearchFilter::default();
+        assert_eq!(
+            search_encounters(&previews(), &filter, 0, 10).unwrap_err(),
+            SearchError::InvalidPage(0)
+        );
+        assert_eq!(
+            search_encounters(&previews(), &filter, 1, 0).unwrap_err(),
+            SearchError::InvalidPageSize(0)
+        );
+    }
+}
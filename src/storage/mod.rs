@@ -0,0 +1,61 @@
+mod sqlite;
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::search::SearchError;
+
+pub use sqlite::*;
+
+#[derive(Debug)]
+pub enum StorageError {
+    Sqlite(rusqlite::Error),
+    Json(serde_json::Error),
+    Io(std::io::Error),
+    Search(SearchError),
+}
+
+impl Display for StorageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Sqlite(err) => write!(f, "{}", err),
+            StorageError::Json(err) => write!(f, "{}", err),
+            StorageError::Io(err) => write!(f, "{}", err),
+            StorageError::Search(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StorageError::Sqlite(err) => Some(err),
+            StorageError::Json(err) => Some(err),
+            StorageError::Io(err) => Some(err),
+            StorageError::Search(err) => Some(err),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(err: rusqlite::Error) -> Self {
+        StorageError::Sqlite(err)
+    }
+}
+
+impl From<serde_json::Error> for StorageError {
+    fn from(err: serde_json::Error) -> Self {
+        StorageError::Json(err)
+    }
+}
+
+impl From<std::io::Error> for StorageError {
+    fn from(err: std::io::Error) -> Self {
+        StorageError::Io(err)
+    }
+}
+
+impl From<SearchError> for StorageError {
+    fn from(err: SearchError) -> Self {
+        StorageError::Search(err)
+    }
+}
@@ -0,0 +1,742 @@
+use std::io::{Read, Write};
+use std::path::Path;
+
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use hashbrown::HashMap;
+use rusqlite::types::{Type, Value, ValueRef};
+use rusqlite::{Connection, OptionalExtension, Row, Transaction, params, params_from_iter};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::models::*;
+use crate::search::{EncounterQuery, SortKey, SortOrder, validate_page};
+
+use super::StorageError;
+
+/// Value of the `encounter.version` column for rows written by this crate.
+pub const DB_VERSION: i64 = 5;
+
+/// Tables of the loa-logs `encounters.db`.
+///
+/// Large JSON columns (`buffs`, `debuffs`, `applied_shield_buffs`, `boss_hp_log` and `entity.skills`)
+/// are stored as gzip-compressed BLOBs. Plain TEXT values written by older versions are read as well.
+/// `encounter_preview.players` holds `class_id:name` pairs separated by commas, ordered by damage.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS encounter (
+    id INTEGER PRIMARY KEY,
+    last_combat_packet INTEGER,
+    total_damage_dealt INTEGER,
+    top_damage_dealt INTEGER,
+    total_damage_taken INTEGER,
+    top_damage_taken INTEGER,
+    dps INTEGER,
+    buffs BLOB,
+    debuffs BLOB,
+    total_shielding INTEGER DEFAULT 0,
+    total_effective_shielding INTEGER DEFAULT 0,
+    applied_shield_buffs BLOB,
+    misc TEXT,
+    version INTEGER NOT NULL DEFAULT 5,
+    boss_hp_log BLOB,
+    stagger_stats TEXT
+);
+CREATE TABLE IF NOT EXISTS entity (
+    name TEXT,
+    encounter_id INTEGER NOT NULL,
+    npc_id INTEGER,
+    entity_type TEXT,
+    class_id INTEGER,
+    class TEXT,
+    gear_score REAL,
+    current_hp INTEGER,
+    max_hp INTEGER,
+    is_dead INTEGER,
+    skills BLOB,
+    damage_stats TEXT,
+    skill_stats TEXT,
+    dps INTEGER,
+    character_id INTEGER,
+    engravings TEXT,
+    gear_hash TEXT,
+    ark_passive_active INTEGER,
+    spec TEXT,
+    ark_passive_data TEXT,
+    PRIMARY KEY (name, encounter_id),
+    FOREIGN KEY (encounter_id) REFERENCES encounter (id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS entity_encounter_id_idx ON entity (encounter_id DESC);
+CREATE TABLE IF NOT EXISTS encounter_preview (
+    id INTEGER PRIMARY KEY,
+    fight_start INTEGER,
+    current_boss TEXT,
+    duration INTEGER,
+    players TEXT,
+    difficulty TEXT,
+    local_player TEXT,
+    my_dps INTEGER,
+    favorite BOOLEAN NOT NULL DEFAULT 0,
+    cleared BOOLEAN,
+    boss_only_damage BOOLEAN NOT NULL DEFAULT 0,
+    FOREIGN KEY (id) REFERENCES encounter (id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS encounter_preview_favorite_idx ON encounter_preview (favorite);
+CREATE INDEX IF NOT EXISTS encounter_preview_fight_start_idx ON encounter_preview (fight_start);
+CREATE INDEX IF NOT EXISTS encounter_preview_current_boss_idx ON encounter_preview (current_boss);
+CREATE VIRTUAL TABLE IF NOT EXISTS encounter_search USING fts5(
+    current_boss, players, columnsize=0, detail=full,
+    tokenize='trigram remove_diacritics 1',
+    content=encounter_preview, content_rowid=id
+);
+CREATE TRIGGER IF NOT EXISTS encounter_preview_ai AFTER INSERT ON encounter_preview BEGIN
+    INSERT INTO encounter_search (rowid, current_boss, players) VALUES (new.id, new.current_boss, new.players);
+END;
+CREATE TRIGGER IF NOT EXISTS encounter_preview_ad AFTER DELETE ON encounter_preview BEGIN
+    INSERT INTO encounter_search (encounter_search, rowid, current_boss, players) VALUES ('delete', old.id, old.current_boss, old.players);
+END;
+CREATE TRIGGER IF NOT EXISTS encounter_preview_au AFTER UPDATE OF current_boss, players ON encounter_preview BEGIN
+    INSERT INTO encounter_search (encounter_search, rowid, current_boss, players) VALUES ('delete', old.id, old.current_boss, old.players);
+    INSERT INTO encounter_search (rowid, current_boss, players) VALUES (new.id, new.current_boss, new.players);
+END;
+";
+
+const PREVIEW_COLUMNS: &str =
+    "id, fight_start, current_boss, duration, players, difficulty, local_player, my_dps, favorite, cleared, boss_only_damage";
+
+/// Encounter database using the loa-logs schema, so existing `encounters.db` files open directly.
+pub struct EncounterStore {
+    connection: Connection,
+}
+
+impl EncounterStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, StorageError> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    /// Creates missing tables, leaving existing ones untouched.
+    pub fn from_connection(connection: Connection) -> Result<Self, StorageError> {
+        connection.pragma_update(None, "foreign_keys", true)?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection })
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Saves the encounter with its entities and preview, returning the new encounter id.
+    pub fn insert(&mut self, encounter: &Encounter) -> Result<i64, StorageError> {
+        let transaction = self.connection.transaction()?;
+        let id = insert_encounter(&transaction, encounter)?;
+        transaction.commit()?;
+        Ok(id)
+    }
+
+    pub fn encounter(&self, id: i64) -> Result<Option<Encounter>, StorageError> {
+        let encounter = self
+            .connection
+            .query_row(
+                "SELECT e.*, p.fight_start, p.current_boss, p.duration, p.difficulty, p.local_player,
+                        p.favorite, p.cleared, p.boss_only_damage
+                 FROM encounter e JOIN encounter_preview p ON p.id = e.id
+                 WHERE e.id = ?",
+                [id],
+                |row| Ok(read_encounter(row)),
+            )
+            .optional()?
+            .transpose()?;
+
+        let Some(mut encounter) = encounter else {
+            return Ok(None);
+        };
+
+        let mut statement = self.connection.prepare("SELECT * FROM entity WHERE encounter_id = ?")?;
+        let mut rows = statement.query([id])?;
+        while let Some(row) = rows.next()? {
+            let entity = read_entity(row)?;
+            encounter.entities.insert(entity.name.clone(), entity);
+        }
+
+        encounter.current_boss = encounter.entities.get(&encounter.current_boss_name).cloned();
+
+        Ok(Some(encounter))
+    }
+
+    /// Lists the 1-based `page` of previews matching `filter`, using the same rules as
+    /// [`EncounterQuery::search`](crate::search::EncounterQuery::search).
+    pub fn previews(&self, filter: &SearchFilter, page: i32, logs_per_page: i32) -> Result<EncountersOverview, StorageError> {
+        validate_page(page, logs_per_page)?;
+        let query = EncounterQuery::try_from(filter)?;
+        let (condition, mut values) = where_clause(&query);
+
+        let total_encounters = self.count(&condition, &values)?;
+
+        let direction = match query.order {
+            SortOrder::Ascending => "ASC",
+            SortOrder::Descending => "DESC",
+        };
+        let sql = format!(
+            "SELECT {} FROM encounter_preview WHERE {} ORDER BY {} {dir}, id {dir} LIMIT ? OFFSET ?",
+            PREVIEW_COLUMNS,
+            condition,
+            sort_column(query.sort),
+            dir = direction
+        );
+        values.push(Value::Integer(logs_per_page as i64));
+        values.push(Value::Integer((page as i64 - 1) * logs_per_page as i64));
+
+        let mut statement = self.connection.prepare(&sql)?;
+        let encounters = statement
+            .query_map(params_from_iter(values), read_preview)?
+            .collect::<Result<_, _>>()?;
+
+        Ok(EncountersOverview {
+            encounters,
+            total_encounters,
+        })
+    }
+
+    /// Flips the favorite flag, returning the new value or `None` when the encounter does not exist.
+    pub fn toggle_favorite(&self, id: i64) -> Result<Option<bool>, StorageError> {
+        let favorite = self
+            .connection
+            .query_row(
+                "UPDATE encounter_preview SET favorite = NOT favorite WHERE id = ? RETURNING favorite",
+                [id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(favorite)
+    }
+
+    /// Deletes the encounter together with its entities and preview.
+    pub fn delete(&self, id: i64) -> Result<bool, StorageError> {
+        Ok(self.connection.execute("DELETE FROM encounter WHERE id = ?", [id])? > 0)
+    }
+
+    pub fn db_info(&self, filter: &SearchFilter) -> Result<EncounterDbInfo, StorageError> {
+        let page_count: i64 = self.connection.pragma_query_value(None, "page_count", |row| row.get(0))?;
+        let page_size: i64 = self.connection.pragma_query_value(None, "page_size", |row| row.get(0))?;
+
+        let (condition, values) = where_clause(&EncounterQuery::try_from(filter)?);
+
+        Ok(EncounterDbInfo {
+            size: format_size(page_count * page_size),
+            total_encounters: self.count("1", &[])?,
+            total_encounters_filtered: self.count(&condition, &values)?,
+        })
+    }
+
+    fn count(&self, condition: &str, values: &[Value]) -> Result<i32, StorageError> {
+        let sql = format!("SELECT COUNT(*) FROM encounter_preview WHERE {}", condition);
+        Ok(self.connection.query_row(&sql, params_from_iter(values), |row| row.get(0))?)
+    }
+}
+
+fn insert_encounter(transaction: &Transaction, encounter: &Encounter) -> Result<i64, StorageError> {
+    let stats = &encounter.encounter_damage_stats;
+
+    transaction.execute(
+        "INSERT INTO encounter (
+            last_combat_packet, total_damage_dealt, top_damage_dealt, total_damage_taken, top_damage_taken, dps,
+            buffs, debuffs, total_shielding, total_effective_shielding, applied_shield_buffs, misc, version,
+            boss_hp_log, stagger_stats
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        params![
+            encounter.last_combat_packet,
+            stats.total_damage_dealt,
+            stats.top_damage_dealt,
+            stats.total_damage_taken,
+            stats.top_damage_taken,
+            stats.dps,
+            compress_json(&stats.buffs)?,
+            compress_json(&stats.debuffs)?,
+            stats.total_shielding as i64,
+            stats.total_effective_shielding as i64,
+            compress_json(&stats.applied_shield_buffs)?,
+            stats.misc.as_ref().map(serde_json::to_string).transpose()?,
+            DB_VERSION,
+            compress_json(&stats.boss_hp_log)?,
+            stats.stagger_stats.as_ref().map(serde_json::to_string).transpose()?,
+        ],
+    )?;
+    let id = transaction.last_insert_rowid();
+
+    let mut statement = transaction.prepare(
+        "INSERT INTO entity (
+            name, encounter_id, npc_id, entity_type, class_id, class, gear_score, current_hp, max_hp, is_dead,
+            skills, damage_stats, skill_stats, dps, character_id, engravings, gear_hash, ark_passive_active,
+            spec, ark_passive_data
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )?;
+    for entity in encounter.entities.values() {
+        statement.execute(params![
+            entity.name,
+            id,
+            entity.npc_id,
+            entity.entity_type.to_string(),
+            entity.class_id,
+            entity.class,
+            entity.gear_score,
+            entity.current_hp,
+            entity.max_hp,
+            entity.is_dead,
+            compress_json(&entity.skills)?,
+            serde_json::to_string(&entity.damage_stats)?,
+            serde_json::to_string(&entity.skill_stats)?,
+            entity.damage_stats.dps,
+            entity.character_id as i64,
+            entity.engraving_data.as_ref().map(serde_json::to_string).transpose()?,
+            entity.gear_hash,
+            entity.ark_passive_active,
+            entity.spec,
+            entity.ark_passive_data.as_ref().map(serde_json::to_string).transpose()?,
+        ])?;
+    }
+
+    let preview = EncounterPreview::from_encounter(encounter, id as i32);
+    let players = preview
+        .classes
+        .iter()
+        .zip(&preview.names)
+        .map(|(class_id, name)| format!("{}:{}", class_id, name))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    transaction.execute(
+        &format!("INSERT INTO encounter_preview ({}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)", PREVIEW_COLUMNS),
+        params![
+            id,
+            preview.fight_start,
+            preview.boss_name,
+            preview.duration,
+            players,
+            preview.difficulty,
+            preview.local_player,
+            preview.my_dps,
+            preview.favorite,
+            preview.cleared,
+            preview.boss_only_damage,
+        ],
+    )?;
+
+    Ok(id)
+}
+
+fn read_encounter(row: &Row) -> Result<Encounter, StorageError> {
+    Ok(Encounter {
+        last_combat_packet: row.get::<_, Option<i64>>("last_combat_packet")?.unwrap_or_default(),
+        fight_start: row.get::<_, Option<i64>>("fight_start")?.unwrap_or_default(),
+        local_player: row.get::<_, Option<String>>("local_player")?.unwrap_or_default(),
+        current_boss_name: row.get::<_, Option<String>>("current_boss")?.unwrap_or_default(),
+        duration: row.get::<_, Option<i64>>("duration")?.unwrap_or_default(),
+        difficulty: row.get("difficulty")?,
+        favorite: row.get("favorite")?,
+        cleared: row.get::<_, Option<bool>>("cleared")?.unwrap_or_default(),
+        boss_only_damage: row.get("boss_only_damage")?,
+        encounter_damage_stats: EncounterDamageStats {
+            total_damage_dealt: row.get::<_, Option<i64>>("total_damage_dealt")?.unwrap_or_default(),
+            top_damage_dealt: row.get::<_, Option<i64>>("top_damage_dealt")?.unwrap_or_default(),
+            total_damage_taken: row.get::<_, Option<i64>>("total_damage_taken")?.unwrap_or_default(),
+            top_damage_taken: row.get::<_, Option<i64>>("top_damage_taken")?.unwrap_or_default(),
+            dps: row.get::<_, Option<i64>>("dps")?.unwrap_or_default(),
+            buffs: read_json(row, "buffs")?,
+            debuffs: read_json(row, "debuffs")?,
+            total_shielding: row.get::<_, Option<i64>>("total_shielding")?.unwrap_or_default() as u64,
+            total_effective_shielding: row.get::<_, Option<i64>>("total_effective_shielding")?.unwrap_or_default() as u64,
+            applied_shield_buffs: read_json(row, "applied_shield_buffs")?,
+            misc: read_json(row, "misc")?,
+            boss_hp_log: read_json(row, "boss_hp_log")?,
+            stagger_stats: read_json(row, "stagger_stats")?,
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+}
+
+fn read_entity(row: &Row) -> Result<EncounterEntity, StorageError> {
+    Ok(EncounterEntity {
+        name: row.get("name")?,
+        npc_id: row.get("npc_id")?,
+        entity_type: row
+            .get::<_, Option<String>>("entity_type")?
+            .and_then(|entity_type| entity_type.parse().ok())
+            .unwrap_or_default(),
+        class_id: row.get("class_id")?,
+        class: row.get::<_, Option<String>>("class")?.unwrap_or_default(),
+        gear_score: row.get::<_, Option<f32>>("gear_score")?.unwrap_or_default(),
+        current_hp: row.get::<_, Option<i64>>("current_hp")?.unwrap_or_default(),
+        max_hp: row.get::<_, Option<i64>>("max_hp")?.unwrap_or_default(),
+        is_dead: row.get::<_, Option<bool>>("is_dead")?.unwrap_or_default(),
+        skills: read_json(row, "skills")?,
+        damage_stats: read_json(row, "damage_stats")?,
+        skill_stats: read_json(row, "skill_stats")?,
+        character_id: row.get::<_, Option<i64>>("character_id")?.unwrap_or_default() as u64,
+        engraving_data: read_json(row, "engravings")?,
+        gear_hash: row.get("gear_hash")?,
+        ark_passive_active: row.get("ark_passive_active")?,
+        spec: row.get("spec")?,
+        ark_passive_data: read_json(row, "ark_passive_data")?,
+        ..Default::default()
+    })
+}
+
+fn read_preview(row: &Row) -> rusqlite::Result<EncounterPreview> {
+    let players: Option<String> = row.get("players")?;
+    let (classes, names) = match players.as_deref() {
+        None | Some("") => (Vec::new(), Vec::new()),
+        Some(players) => parse_players(players).map_err(|reason| {
+            let index = row.as_ref().column_index("players").unwrap_or_default();
+            rusqlite::Error::FromSqlConversionFailure(index, Type::Text, reason.into())
+        })?,
+    };
+
+    Ok(EncounterPreview {
+        id: row.get("id")?,
+        fight_start: row.get::<_, Option<i64>>("fight_start")?.unwrap_or_default(),
+        boss_name: row.get::<_, Option<String>>("current_boss")?.unwrap_or_default(),
+        duration: row.get::<_, Option<i64>>("duration")?.unwrap_or_default(),
+        classes,
+        names,
+        difficulty: row.get("difficulty")?,
+        local_player: row.get::<_, Option<String>>("local_player")?.unwrap_or_default(),
+        my_dps: row.get::<_, Option<i64>>("my_dps")?.unwrap_or_default(),
+        favorite: row.get("favorite")?,
+        cleared: row.get::<_, Option<bool>>("cleared")?.unwrap_or_default(),
+        boss_only_damage: row.get("boss_only_damage")?,
+    })
+}
+
+/// Splits `encounter_preview.players` into class ids and names, aligned by index.
+fn parse_players(players: &str) -> Result<(Vec<i32>, Vec<String>), String> {
+    let players = players
+        .split(',')
+        .map(|player| {
+            let (class_id, name) = player
+                .split_once(':')
+                .ok_or_else(|| format!("player {:?} is not a class_id:name pair", player))?;
+            let class_id = class_id
+                .parse::<i32>()
+                .map_err(|_| format!("player {:?} has an invalid class id", player))?;
+            Ok((class_id, name.to_string()))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(players.into_iter().unzip())
+}
+
+/// Mirrors [`EncounterQuery::matches`] as an SQL condition over `encounter_preview`.
+fn where_clause(query: &EncounterQuery) -> (String, Vec<Value>) {
+    let mut conditions = vec!["duration >= ?".to_string()];
+    let mut values = vec![Value::Integer(query.min_duration as i64 * 1000)];
+
+    if query.max_duration > 0 {
+        conditions.push("duration < ?".to_string());
+        values.push(Value::Integer(query.max_duration as i64 * 1000));
+    }
+
+    if !query.bosses.is_empty() {
+        let placeholders = vec!["?"; query.bosses.len()].join(", ");
+        conditions.push(format!("current_boss IN ({})", placeholders));
+        values.extend(query.bosses.iter().cloned().map(Value::Text));
+    }
+
+    if query.cleared_only {
+        conditions.push("cleared = 1".to_string());
+    }
+
+    if query.favorites_only {
+        conditions.push("favorite = 1".to_string());
+    }
+
+    if let Some(difficulty) = &query.difficulty {
+        conditions.push("difficulty = ?".to_string());
+        values.push(Value::Text(difficulty.clone()));
+    }
+
+    if query.boss_only_damage {
+        conditions.push("boss_only_damage = 1".to_string());
+    }
+
+    (conditions.join(" AND "), values)
+}
+
+fn sort_column(sort: SortKey) -> &'static str {
+    match sort {
+        SortKey::Id => "id",
+        SortKey::FightStart => "fight_start",
+        SortKey::Duration => "duration",
+        SortKey::MyDps => "my_dps",
+        SortKey::BossName => "current_boss",
+    }
+}
+
+fn compress_json<T: Serialize>(value: &T) -> Result<Vec<u8>, StorageError> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    serde_json::to_writer(&mut encoder, value)?;
+    Ok(encoder.finish()?)
+}
+
+/// Reads a JSON column stored either as gzip-compressed BLOB or as plain TEXT.
+fn read_json<T: DeserializeOwned + Default>(row: &Row, column: &str) -> Result<T, StorageError> {
+    match row.get_ref(column)? {
+        ValueRef::Null => Ok(T::default()),
+        ValueRef::Blob(bytes) => {
+            let mut json = Vec::new();
+            GzDecoder::new(bytes).read_to_end(&mut json)?;
+            Ok(serde_json::from_slice(&json)?)
+        }
+        ValueRef::Text(json) => Ok(serde_json::from_slice(json)?),
+        other => {
+            let index = row.as_ref().column_index(column)?;
+            Err(rusqlite::Error::InvalidColumnType(index, column.to_string(), other.data_type()).into())
+        }
+    }
+}
+
+fn format_size(bytes: i64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOA_LOGS_DB_SQL: &str = include_str!("../../tests/fixtures/encounters_db.sql");
+
+    fn encounter(boss: &str, fight_start: i64, duration: i64, cleared: bool) -> Encounter {
+        let player = EncounterEntity {
+            name: "Alice".to_string(),
+            entity_type: EntityType::Player,
+            character_id: 1,
+            class_id: Class::Berserker as u32,
+            class: Class::Berserker.as_ref().to_string(),
+            skills: [(
+                16140,
+                Skill {
+                    id: 16140,
+                    name: "Bloody Rush".to_string(),
+                    total_damage: 5000,
+                    skill_cast_log: vec![SkillCast {
+                        timestamp: 1000,
+                        hits: vec![SkillHit {
+                            timestamp: 1000,
+                            damage: 5000,
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+            )]
+            .into_iter()
+            .collect(),
+            damage_stats: DamageStats {
+                damage_dealt: 5000,
+                dps: 50,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        Encounter {
+            fight_start,
+            last_combat_packet: fight_start + duration,
+            duration,
+            local_player: "Alice".to_string(),
+            current_boss_name: boss.to_string(),
+            cleared,
+            entities: [(player.name.clone(), player)].into_iter().collect(),
+            encounter_damage_stats: EncounterDamageStats {
+                total_damage_dealt: 5000,
+                boss_hp_log: [(boss.to_string(), vec![BossHpLog::new(0, 100, 1.0)])].into_iter().collect(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn should_insert_and_load_encounter() {
+        let mut store = EncounterStore::open_in_memory().unwrap();
+
+        let id = store.insert(&encounter("Thaemine", 1000, 100_000, true)).unwrap();
+        let loaded = store.encounter(id).unwrap().unwrap();
+
+        assert_eq!(loaded.current_boss_name, "Thaemine");
+        assert_eq!(loaded.duration, 100_000);
+        assert!(loaded.cleared);
+        assert_eq!(loaded.encounter_damage_stats.boss_hp_log["Thaemine"].len(), 1);
+        let alice = &loaded.entities["Alice"];
+        assert_eq!(alice.entity_type, EntityType::Player);
+        assert_eq!(alice.skills[&16140].skill_cast_log[0].hits[0].damage, 5000);
+        assert!(store.encounter(id + 1).unwrap().is_none());
+    }
+
+    #[test]
+    fn should_list_previews_by_filter() {
+        let mut store = EncounterStore::open_in_memory().unwrap();
+        store.insert(&encounter("Thaemine", 1000, 600_000, true)).unwrap();
+        store.insert(&encounter("Echidna", 2000, 300_000, false)).unwrap();
+        store.insert(&encounter("Echidna", 3000, 30_000, true)).unwrap();
+
+        let overview = store.previews(&SearchFilter::default(), 1, 2).unwrap();
+        assert_eq!(overview.total_encounters, 3);
+        assert_eq!(overview.encounters.iter().map(|preview| preview.id).collect::<Vec<_>>(), [3, 2]);
+        assert_eq!(overview.encounters[0].names, ["Alice"]);
+        assert_eq!(overview.encounters[0].classes, [Class::Berserker as i32]);
+        assert_eq!(overview.encounters[0].my_dps, 50);
+
+        let filter = SearchFilter {
+            bosses: vec!["Echidna".to_string()],
+            cleared: true,
+            ..Default::default()
+        };
+        let overview = store.previews(&filter, 1, 10).unwrap();
+        assert_eq!(overview.total_encounters, 1);
+        assert_eq!(overview.encounters[0].id, 3);
+    }
+
+    #[test]
+    fn should_reject_malformed_preview_players() {
+        let mut store = EncounterStore::open_in_memory().unwrap();
+        let id = store.insert(&encounter("Thaemine", 1000, 600_000, true)).unwrap();
+
+        for players in ["102:Alice,Bob", "x:Alice"] {
+            store
+                .connection()
+                .execute("UPDATE encounter_preview SET players = ? WHERE id = ?", params![players, id])
+                .unwrap();
+
+            let result = store.previews(&SearchFilter::default(), 1, 10);
+
+            assert!(matches!(result, Err(StorageError::Sqlite(rusqlite::Error::FromSqlConversionFailure(..)))));
+        }
+
+        store
+            .connection()
+            .execute("UPDATE encounter_preview SET players = '' WHERE id = ?", [id])
+            .unwrap();
+        let overview = store.previews(&SearchFilter::default(), 1, 10).unwrap();
+        assert!(overview.encounters[0].names.is_empty());
+        assert!(overview.encounters[0].classes.is_empty());
+    }
+
+    #[test]
+    fn should_toggle_favorite_and_delete() {
+        let mut store = EncounterStore::open_in_memory().unwrap();
+        let id = store.insert(&encounter("Thaemine", 1000, 600_000, true)).unwrap();
+
+        assert_eq!(store.toggle_favorite(id).unwrap(), Some(true));
+        let favorites = SearchFilter {
+            favorite: true,
+            ..Default::default()
+        };
+        assert_eq!(store.db_info(&favorites).unwrap().total_encounters_filtered, 1);
+        assert_eq!(store.toggle_favorite(id).unwrap(), Some(false));
+        assert_eq!(store.toggle_favorite(id + 1).unwrap(), None);
+
+        assert!(store.delete(id).unwrap());
+        assert!(!store.delete(id).unwrap());
+        let entities: i64 = store
+            .connection()
+            .query_row("SELECT COUNT(*) FROM entity", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(entities, 0);
+        assert_eq!(store.db_info(&SearchFilter::default()).unwrap().total_encounters, 0);
+    }
+
+    #[test]
+    fn should_read_uncompressed_json_columns() {
+        let mut store = EncounterStore::open_in_memory().unwrap();
+        let id = store.insert(&encounter("Thaemine", 1000, 600_000, true)).unwrap();
+        store
+            .connection()
+            .execute("UPDATE entity SET skills = '{}' WHERE encounter_id = ?", [id])
+            .unwrap();
+
+        let loaded = store.encounter(id).unwrap().unwrap();
+
+        assert!(loaded.entities["Alice"].skills.is_empty());
+    }
+
+    #[test]
+    fn should_load_encounter_with_null_columns() {
+        let mut store = EncounterStore::open_in_memory().unwrap();
+        let id = store.insert(&encounter("Thaemine", 1000, 600_000, true)).unwrap();
+        store
+            .connection()
+            .execute(
+                "UPDATE encounter SET last_combat_packet = NULL, total_damage_dealt = NULL, top_damage_dealt = NULL,
+                    total_damage_taken = NULL, top_damage_taken = NULL, dps = NULL, total_shielding = NULL WHERE id = ?",
+                [id],
+            )
+            .unwrap();
+        store
+            .connection()
+            .execute("UPDATE encounter_preview SET fight_start = NULL, duration = NULL WHERE id = ?", [id])
+            .unwrap();
+
+        let loaded = store.encounter(id).unwrap().unwrap();
+
+        assert_eq!(loaded.last_combat_packet, 0);
+        assert_eq!(loaded.duration, 0);
+        assert_eq!(loaded.encounter_damage_stats.dps, 0);
+        assert_eq!(loaded.encounter_damage_stats.total_shielding, 0);
+    }
+
+    #[test]
+    fn should_open_existing_loa_logs_database() {
+        let path = std::env::temp_dir().join(format!("lost-metrics-core-{}.db", uuid::Uuid::new_v4()));
+        Connection::open(&path).unwrap().execute_batch(LOA_LOGS_DB_SQL).unwrap();
+
+        let store = EncounterStore::open(&path).unwrap();
+        let compressed = store.encounter(1).unwrap().unwrap();
+        let uncompressed = store.encounter(2).unwrap().unwrap();
+        let overview = store.previews(&SearchFilter::default(), 1, 10).unwrap();
+        drop(store);
+        std::fs::remove_file(&path).unwrap();
+
+        let alice = &compressed.entities["Alice"];
+        assert_eq!(alice.skills[&16140].total_damage, 3_500_000);
+        assert_eq!(alice.damage_stats.dps_average, [0, 375_000]);
+        assert_eq!(alice.engraving_data.as_deref(), Some(&["Mayhem".to_string(), "Grudge".to_string()][..]));
+        assert_eq!(compressed.current_boss.unwrap().max_hp, 100_000_000);
+        assert_eq!(compressed.encounter_damage_stats.boss_hp_log["Thaemine the Lightqueller"].len(), 2);
+        assert_eq!(compressed.encounter_damage_stats.stagger_stats.unwrap().average, 12.5);
+        assert!(compressed.encounter_damage_stats.misc.unwrap().raid_clear.unwrap());
+
+        assert_eq!(uncompressed.entities["Bob"].skills[&16140].name, "Bloody Rush");
+        assert_eq!(uncompressed.encounter_damage_stats.total_shielding, 0);
+        assert!(uncompressed.encounter_damage_stats.misc.is_none());
+
+        assert_eq!(overview.total_encounters, 2);
+        assert_eq!(overview.encounters[0].names, ["Bob"]);
+        assert_eq!(overview.encounters[1].classes, [Class::Berserker as i32]);
+    }
+
+    #[test]
+    fn should_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(4096), "4.0 KB");
+        assert_eq!(format_size(5 * 1024 * 1024 + 512 * 1024), "5.5 MB");
+    }
+}
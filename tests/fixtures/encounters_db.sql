@@ -0,0 +1,88 @@
+-- An encounters.db in the loa-logs layout, reconstructed from its schema and upgrade steps
+-- rather than captured from an installation. Later columns are appended by ALTER TABLE, so
+-- their order differs from a freshly created schema. Encounter 1 stores its JSON columns
+-- gzip-compressed, encounter 2 as plain TEXT like rows written before compression.
+CREATE TABLE encounter (
+    id INTEGER PRIMARY KEY,
+    last_combat_packet INTEGER,
+    total_damage_dealt INTEGER,
+    top_damage_dealt INTEGER,
+    total_damage_taken INTEGER,
+    top_damage_taken INTEGER,
+    dps INTEGER,
+    buffs TEXT,
+    debuffs TEXT,
+    misc TEXT
+);
+ALTER TABLE encounter ADD COLUMN total_shielding INTEGER DEFAULT 0;
+ALTER TABLE encounter ADD COLUMN total_effective_shielding INTEGER DEFAULT 0;
+ALTER TABLE encounter ADD COLUMN applied_shield_buffs TEXT;
+ALTER TABLE encounter ADD COLUMN version INTEGER NOT NULL DEFAULT 5;
+ALTER TABLE encounter ADD COLUMN boss_hp_log BLOB;
+ALTER TABLE encounter ADD COLUMN stagger_stats TEXT;
+CREATE TABLE entity (
+    name TEXT,
+    encounter_id INTEGER NOT NULL,
+    npc_id INTEGER,
+    entity_type TEXT,
+    class_id INTEGER,
+    class TEXT,
+    gear_score REAL,
+    current_hp INTEGER,
+    max_hp INTEGER,
+    is_dead INTEGER,
+    skills TEXT,
+    damage_stats TEXT,
+    skill_stats TEXT,
+    dps INTEGER,
+    PRIMARY KEY (name, encounter_id),
+    FOREIGN KEY (encounter_id) REFERENCES encounter (id) ON DELETE CASCADE
+);
+ALTER TABLE entity ADD COLUMN character_id INTEGER;
+ALTER TABLE entity ADD COLUMN engravings TEXT;
+ALTER TABLE entity ADD COLUMN gear_hash TEXT;
+ALTER TABLE entity ADD COLUMN ark_passive_active INTEGER;
+ALTER TABLE entity ADD COLUMN spec TEXT;
+ALTER TABLE entity ADD COLUMN ark_passive_data TEXT;
+CREATE INDEX entity_encounter_id_idx ON entity (encounter_id DESC);
+CREATE TABLE encounter_preview (
+    id INTEGER PRIMARY KEY,
+    fight_start INTEGER,
+    current_boss TEXT,
+    duration INTEGER,
+    players TEXT,
+    difficulty TEXT,
+    local_player TEXT,
+    my_dps INTEGER,
+    favorite BOOLEAN NOT NULL DEFAULT 0,
+    cleared BOOLEAN,
+    boss_only_damage BOOLEAN NOT NULL DEFAULT 0,
+    FOREIGN KEY (id) REFERENCES encounter (id) ON DELETE CASCADE
+);
+
+INSERT INTO encounter (id, last_combat_packet, total_damage_dealt, top_damage_dealt, total_damage_taken, top_damage_taken,
+    dps, buffs, debuffs, misc, total_shielding, total_effective_shielding, applied_shield_buffs, version, boss_hp_log, stagger_stats)
+VALUES (1, 1700000020000, 3500000, 3500000, 30000, 30000, 175000, X'1F8B0800000000000203ABAE050043BFA6A302000000', X'1F8B0800000000000203ABAE050043BFA6A302000000', '{"raidClear":true,"partyInfo":{"0":["Alice"]},"region":"EUC","version":"1.11.2"}', 0, 0, X'1F8B0800000000000203ABAE050043BFA6A302000000', 5,
+    X'1F8B0800000000000203AB560AC9484CCDCDCC4B5528C94855F0C94CCF28292C4DCDC9492D52B28AAE562AC9CC4D55B232D051CA2850B2323480021D25104FCFA05607A6C208AAC4D2CC14A1C2400FC8AD8DAD0500E5B896E065000000', '{"average":12.5,"staggersPerMin":1.5,"log":[[0,0.0],[5,25.0]]}');
+INSERT INTO entity (name, encounter_id, npc_id, entity_type, class_id, class, gear_score, current_hp, max_hp, is_dead,
+    skills, damage_stats, skill_stats, dps, character_id, engravings, gear_hash, ark_passive_active, spec, ark_passive_data)
+VALUES
+    ('Alice', 1, 0, 'Player', 102, 'Berserker', 1680.5, 420000, 450000, 0, X'1F8B0800000000000203AB56323433343150B2AA56CA4C51B202737494F212735395AC949C72F2F3532A15824A8B339474943293F3F3808249D9F1C5D9993939F10686F186267A0579E940B992FC92C41C97C4DCC474A03E63530310D051CA4DAC8089191940C592138B4B8A8116E928656482184640A12230CBB0B616007D31EDBC8E000000', '{"damageDealt":3500000,"damageTaken":30000,"dps":175000,"dpsAverage":[0,375000],"deaths":0}', '{"casts":1,"hits":2,"crits":1,"backAttacks":2,"frontAttacks":0,"counters":0}',
+        175000, 9000001, '["Mayhem","Grudge"]', NULL, 1, 'Mayhem', NULL),
+    ('Thaemine the Lightqueller', 1, 480010, 'Boss', 0, 'Unknown', 0.0, 96500000, 100000000, 0, X'1F8B0800000000000203ABAE050043BFA6A302000000', '{"damageDealt":30000,"damageTaken":3500000}',
+        '{"casts":0,"hits":0,"crits":0,"backAttacks":0,"frontAttacks":0,"counters":0}', 0, 0, NULL, NULL, NULL,
+        NULL, NULL);
+INSERT INTO encounter_preview (id, fight_start, current_boss, duration, players, difficulty, local_player, my_dps, favorite,
+    cleared, boss_only_damage)
+VALUES (1, 1700000000000, 'Thaemine the Lightqueller', 20000, '102:Alice', 'Hard', 'Alice', 175000, 1, 1, 1);
+
+INSERT INTO encounter (id, last_combat_packet, total_damage_dealt, top_damage_dealt, total_damage_taken, top_damage_taken,
+    dps, buffs, debuffs, misc, total_shielding, total_effective_shielding, applied_shield_buffs, version, boss_hp_log, stagger_stats)
+VALUES (2, 1690000030000, 1200000, 1200000, 0, 0, 40000, '{}', '{}', NULL, NULL, NULL, NULL, 2, NULL, NULL);
+INSERT INTO entity (name, encounter_id, npc_id, entity_type, class_id, class, gear_score, current_hp, max_hp, is_dead,
+    skills, damage_stats, skill_stats, dps)
+VALUES ('Bob', 2, 0, 'Player', 204, 'Bard', 1620.0, 300000, 300000, 0, '{"16140":{"id":16140,"name":"Bloody Rush","icon":"bk_skill_01_14.png","totalDamage":3500000,"maxDamage":2000000,"casts":1,"hits":2,"crits":1}}', '{"damageDealt":1200000,"dps":40000}',
+    '{"casts":1,"hits":2,"crits":1,"backAttacks":2,"frontAttacks":0,"counters":0}', 40000);
+INSERT INTO encounter_preview (id, fight_start, current_boss, duration, players, difficulty, local_player, my_dps, favorite,
+    cleared, boss_only_damage)
+VALUES (2, 1690000000000, 'Echidna', 30000, '204:Bob', NULL, 'Bob', 40000, 0, NULL, 0);